@@ -0,0 +1,160 @@
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+use anyhow::Context;
+use chrono::{Datelike, NaiveDate};
+use log::{debug, info, warn};
+use nhl_api::GameType;
+use tokio::time::sleep;
+
+use crate::{
+    data::{
+        models::{games::Game, prediction::Prediction, scores::ScoresResponse},
+        storage::GameStore,
+    },
+    model::state::{State, StateConfig},
+    utils::in_season,
+};
+
+const RETRIES: u32 = 5;
+const SCORES_URL: &str = "https://api-web.nhle.com/v1/score";
+
+/// Walks the daily scores day by day, stores every final regular season game
+/// and feeds it through the models.
+///
+/// The last completed day is checkpointed in the store and every game is
+/// recorded once processed, so an interrupted backfill can be restarted
+/// without counting any game twice. When the database runs in memory mode
+/// the state is only written back every `snapshot_every` days, and resuming
+/// picks up from the last snapshot.
+pub struct Backfill<'a, S> {
+    db: S,
+    http: reqwest::Client,
+    pub state: State<'a, S>,
+    pub ngames: usize,
+    pub snapshot_every: usize,
+}
+
+impl<'a, S: GameStore + Clone + Send + 'a> Backfill<'a, S> {
+    pub fn new(db: S, config: StateConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            db: db.clone(),
+            http: reqwest::Client::new(),
+            state: State::with_config(db, config),
+            ngames: 0,
+            snapshot_every: 30,
+        })
+    }
+
+    /// First day left to process, skipping whatever the checkpoint covers
+    pub fn resume_from(&self, from: NaiveDate) -> rusqlite::Result<NaiveDate> {
+        Ok(match self.db.get_checkpoint()? {
+            Some(last) if last >= from => last.succ_opt().unwrap(),
+            _ => from,
+        })
+    }
+
+    /// Processes every day in `[from, to)` that isn't checkpointed yet, calling
+    /// `on_game` with the predictions made before each new game was applied.
+    pub async fn run(
+        &mut self,
+        from: NaiveDate,
+        to: NaiveDate,
+        mut on_game: impl FnMut(&Game, &[Prediction]) -> anyhow::Result<()>,
+    ) -> anyhow::Result<usize> {
+        let start = self.resume_from(from)?;
+        if start != from {
+            info!("Resuming from checkpoint at {start}");
+        }
+        let mut date = start;
+        let mut days = 0;
+        print!("{} games processed", self.ngames);
+        io::stdout().flush()?;
+        while date < to {
+            if date != start && date.year() % 5 == 0 && (date.month(), date.day()) == (1, 1) {
+                debug!("Taking a break");
+                sleep(Duration::from_mins(1)).await;
+                debug!("Break done");
+            }
+            if !in_season(date.year(), date.month(), date.day()) {
+                date = NaiveDate::from_ymd_opt(date.year(), 10, 1).unwrap();
+                continue;
+            }
+            let scores = match self.fetch(date).await {
+                Ok(scores) => scores,
+                Err(err) => {
+                    // Nothing of the day was applied yet, so keep what we have
+                    self.db.snapshot()?;
+                    return Err(err);
+                }
+            };
+            if scores.games.is_empty() {
+                sleep(Duration::from_millis(100)).await;
+            }
+            // The whole day is committed at once, so the checkpoint never
+            // gets ahead of the games it covers
+            let db = self.db.clone();
+            db.transaction(|| -> anyhow::Result<()> {
+                for game in &scores.games {
+                    if !game.game_state.is_final() || game.game_type != GameType::RegularSeason {
+                        continue;
+                    }
+                    if db.is_processed(game.id)? {
+                        debug!("Skipping game {} which was already processed", game.id);
+                        continue;
+                    }
+                    let game = Game::from(game);
+                    db.add_game(&game)?;
+                    let predictions = self.state.process_game(&game)?;
+                    self.ngames += 1;
+                    print!("\r{} game(s) processed", self.ngames);
+                    io::stdout().flush()?;
+                    on_game(&game, &predictions)?;
+                }
+                db.set_checkpoint(date)?;
+                Ok(())
+            })?;
+            days += 1;
+            if days % self.snapshot_every.max(1) == 0 {
+                db.snapshot()?;
+            }
+            date = date.succ_opt().unwrap();
+        }
+        self.db.snapshot()?;
+        print!("\r");
+        io::stdout().flush()?;
+        Ok(self.ngames)
+    }
+
+    async fn fetch(&self, date: NaiveDate) -> anyhow::Result<ScoresResponse> {
+        let url = format!("{SCORES_URL}/{}", date.format("%Y-%m-%d"));
+        let mut wait = Duration::from_secs(1);
+        for attempt in 1..=RETRIES {
+            let response = async {
+                self.http
+                    .get(&url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<ScoresResponse>()
+                    .await
+            };
+            match response.await {
+                Ok(scores) => return Ok(scores),
+                Err(err) if attempt < RETRIES => {
+                    warn!("Failed to fetch scores for {date} ({err}), retrying in {wait:?}");
+                    sleep(wait).await;
+                    wait *= 2;
+                }
+                Err(err) => {
+                    return Err(err).with_context(|| {
+                        format!("Could not fetch scores for {date}, rerun to resume")
+                    });
+                }
+            }
+        }
+        unreachable!()
+    }
+}
@@ -0,0 +1,475 @@
+pub mod backfill;
+pub mod backtest;
+pub mod calibrate;
+pub mod plot;
+pub mod predict;
+pub mod rank;
+pub mod replay;
+pub mod reset;
+pub mod seed;
+pub mod train;
+
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use log::info;
+
+use crate::{
+    DATA_PATH, IMAGE_PATH_GIF, PATH,
+    data::{db::DataBase, models::last10::HISTORY},
+    model::{
+        calibration,
+        calibrator::CalibrationMethod,
+        goals::GoalFormConfig,
+        historical::{HistoricalConfig, HistoricalPrior, Venues},
+        last10::FormConfig,
+        metrics,
+        poisson::PoissonConfig,
+        ranker::RankerConfig,
+        registry::ModelKind,
+        state::{State, StateConfig},
+    },
+    rating::{
+        home::{self, HOME_SCORE},
+        margin::{MarginCurve, MarginWeight},
+        overtime::OvertimePolicy,
+        season::SeasonTransform,
+        system::{RatingSystem, SystemConfig},
+    },
+};
+
+/// Rate NHL teams and predict their games
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Path to the SQLite database
+    #[arg(long, global = true, default_value = PATH)]
+    pub db: String,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Add every NHL team along with its last 10 games and head to head records
+    Seed,
+    /// Process the final regular season scores between two dates, resuming
+    /// from the last checkpoint
+    Backfill {
+        /// First day to fetch (YYYY-MM-DD)
+        #[arg(long, default_value = "1955-10-01")]
+        from: NaiveDate,
+        /// Day to stop at, exclusive (YYYY-MM-DD)
+        #[arg(long, default_value = "2025-04-20")]
+        to: NaiveDate,
+        /// Where to write the per-game model scores. Defaults to
+        /// data/metrics.csv when it holds the same models' scores, or to a
+        /// file next to it named after the models' columns.
+        #[arg(long)]
+        data: Option<String>,
+        /// Keep the model state in memory and only write it back periodically
+        #[arg(long)]
+        in_memory: bool,
+        /// Days between snapshots of the in-memory state
+        #[arg(long, default_value_t = 30)]
+        snapshot_every: usize,
+        #[command(flatten)]
+        model: ModelArgs,
+    },
+    /// Rebuild the ratings, head to heads and last 10 games from the stored
+    /// games, without the network
+    Replay {
+        /// Number of games applied per transaction
+        #[arg(long, default_value_t = 1000)]
+        batch: usize,
+        /// Keep the model state in memory and write it back once done
+        #[arg(long)]
+        in_memory: bool,
+        /// Where to draw the models' reliability diagram
+        #[arg(long)]
+        reliability: Option<String>,
+        #[command(flatten)]
+        model: ModelArgs,
+    },
+    /// Replay the stored games through several ranker configurations side
+    /// by side and compare how well each predicted them
+    Backtest {
+        /// A configuration to try, as comma separated ranker and scoring
+        /// options without their dashes, e.g. `system=elo,k-factor=20,margin=log`.
+        /// Repeat to compare several.
+        #[arg(long = "run", required = true)]
+        runs: Vec<String>,
+        /// Also break the scores down by season
+        #[arg(long)]
+        by_season: bool,
+    },
+    /// Replay the stored games, then fit a calibrator to each model's
+    /// predictions and store it for `predict`
+    Calibrate {
+        #[arg(long, value_enum, default_value_t)]
+        method: CalibrationMethod,
+        /// Number of games applied per transaction
+        #[arg(long, default_value_t = 1000)]
+        batch: usize,
+        /// Keep the model state in memory and write it back once done
+        #[arg(long)]
+        in_memory: bool,
+        #[command(flatten)]
+        model: ModelArgs,
+    },
+    /// List the top and bottom teams by rating
+    Rank {
+        /// Number of teams to show at either end of the table
+        #[arg(short, default_value_t = 5)]
+        n: u64,
+        /// Rating system to rank by
+        #[arg(long, value_enum, default_value_t)]
+        system: RatingSystem,
+    },
+    /// Pick the winners of the games scheduled on a day
+    Predict {
+        /// Day of the games, defaults to today (YYYY-MM-DD)
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        /// The models as set up for `calibrate`, whose calibrators only apply
+        /// to the same settings
+        #[command(flatten)]
+        model: ModelArgs,
+    },
+    /// Render the model scores as a rotating 3D scatter plot
+    Plot {
+        #[arg(long, default_value = DATA_PATH)]
+        data: String,
+        #[arg(long, default_value = IMAGE_PATH_GIF)]
+        out: String,
+    },
+    /// Fit the linfa classifiers on the model scores
+    Train {
+        #[arg(long, default_value = DATA_PATH)]
+        data: String,
+        /// Number of rows drawn for each bootstrap sample
+        #[arg(long, default_value_t = 20_000)]
+        samples: usize,
+    },
+    /// Drop every table in the database
+    Reset,
+}
+
+/// Tuning for the models
+#[derive(Debug, Args)]
+pub struct ModelArgs {
+    /// Models to run, in order, as a comma separated list
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = ModelKind::ALL)]
+    models: Vec<ModelKind>,
+    #[command(flatten)]
+    ranker: RankerArgs,
+    /// What short head to head records are shrunk toward
+    #[arg(long, value_enum, default_value_t)]
+    h2h_prior: HistoricalPrior,
+    /// Weight of the head to head prior in games, 0 for the raw records
+    #[arg(long, default_value_t = HistoricalConfig::default().prior_strength)]
+    h2h_prior_strength: f64,
+    /// Away teams' share of the results the head to head prior falls back on,
+    /// see `rank` for the stored games'
+    #[arg(long, default_value_t = 1. - HOME_SCORE)]
+    h2h_baseline: f64,
+    /// Only count the head to head meetings of the last this many seasons
+    #[arg(long)]
+    h2h_seasons: Option<u32>,
+    /// Only count the last this many head to head meetings
+    #[arg(long)]
+    h2h_meetings: Option<usize>,
+    /// Years over which a head to head meeting's weight halves
+    #[arg(long)]
+    h2h_half_life: Option<f64>,
+    /// Whether the home team's visits to the away team count toward their
+    /// head to head record
+    #[arg(long, value_enum, default_value_t)]
+    h2h_venues: Venues,
+    /// Recent games the form model looks back at
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u8).range(1..=HISTORY as i64))]
+    form_window: u8,
+    /// Weight of each recent game relative to the next one, between 0 and 1
+    #[arg(long)]
+    form_decay: Option<f64>,
+    /// Recent games the goal form model looks back at
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u8).range(1..=HISTORY as i64))]
+    goal_window: u8,
+    /// Exponent of the goal form model's Pythagorean expectation
+    #[arg(long, default_value_t = GoalFormConfig::default().exponent)]
+    pythagorean_exponent: f64,
+    /// How far each goal above or below the expected moves the Poisson
+    /// model's attack and defence strengths
+    #[arg(long, default_value_t = PoissonConfig::default().learning_rate)]
+    strength_rate: f64,
+    /// Regulation goals of an average away team in the Poisson model, see
+    /// `rank` for the stored games'
+    #[arg(long, default_value_t = PoissonConfig::default().away_rate)]
+    away_goal_rate: f64,
+    /// Regulation goals of an average home team in the Poisson model
+    #[arg(long, default_value_t = PoissonConfig::default().home_rate)]
+    home_goal_rate: f64,
+    #[command(flatten)]
+    scoring: ScoringArgs,
+}
+
+/// Tuning for the ranker, the only model `backtest` runs
+#[derive(Debug, Args)]
+struct RankerArgs {
+    /// Rating system the ranker runs on
+    #[arg(long, value_enum, default_value_t)]
+    system: RatingSystem,
+    /// Skill spread of Weng-Lin and TrueSkill, higher means more luck
+    #[arg(long)]
+    beta: Option<f64>,
+    /// Elo K-factor
+    #[arg(long)]
+    k_factor: Option<f64>,
+    /// Fraction of the gap to the league mean closed between seasons
+    #[arg(long, default_value_t = SeasonTransform::default().regression)]
+    regression: f64,
+    /// Added to every team's uncertainty between seasons
+    #[arg(long, default_value_t = SeasonTransform::default().inflation)]
+    inflation: f64,
+    /// Start relocated and renamed teams from scratch
+    #[arg(long)]
+    no_carry_over: bool,
+    /// Scale rating updates by the margin of victory along this curve
+    #[arg(long, value_enum)]
+    margin: Option<MarginCurve>,
+    /// How much each extra goal adds to the update
+    #[arg(long, default_value_t = MarginWeight::default().scale)]
+    margin_scale: f64,
+    /// How much a favourite's win is discounted
+    #[arg(long, default_value_t = MarginWeight::default().damping)]
+    margin_damping: f64,
+    /// Rating offset for the home team, by default the one giving home teams
+    /// a 55% score. See `rank` for the stored games'.
+    #[arg(long)]
+    home_advantage: Option<f64>,
+}
+
+/// How games are scored, shared by every command running the models
+#[derive(Debug, Args)]
+struct ScoringArgs {
+    /// How games decided in overtime or a shootout are scored
+    #[arg(long, value_enum, default_value_t = OvertimeMode::Binary)]
+    overtime: OvertimeMode,
+    /// Winner's share of an overtime result with `--overtime weighted`
+    #[arg(long, default_value_t = 2. / 3.)]
+    overtime_weight: f64,
+    /// Games in the rolling window of the metrics
+    #[arg(long, default_value_t = metrics::WINDOW)]
+    window: usize,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OvertimeMode {
+    /// Full wins and losses
+    Binary,
+    /// Half a win each
+    Draw,
+    /// Split with `--overtime-weight`
+    Weighted,
+}
+
+impl RankerArgs {
+    /// The ranker's settings, the home advantage set from [`HOME_SCORE`]
+    /// unless given
+    fn config(&self) -> RankerConfig {
+        let mut params = SystemConfig::default();
+        if let Some(beta) = self.beta {
+            params.weng_lin.beta = beta;
+            params.trueskill.beta = beta;
+        }
+        if let Some(k) = self.k_factor {
+            params.elo.k = k;
+        }
+        let home_advantage = self
+            .home_advantage
+            .unwrap_or_else(|| home::offset_for(HOME_SCORE, self.system.new_rating(), &params));
+        RankerConfig {
+            system: self.system,
+            params,
+            season: SeasonTransform {
+                regression: self.regression,
+                inflation: self.inflation,
+            },
+            carry_over: !self.no_carry_over,
+            margin: self.margin.map(|curve| MarginWeight {
+                curve,
+                scale: self.margin_scale,
+                damping: self.margin_damping,
+            }),
+            home_advantage,
+        }
+    }
+}
+
+impl ScoringArgs {
+    fn overtime(&self) -> OvertimePolicy {
+        match self.overtime {
+            OvertimeMode::Binary => OvertimePolicy::Binary,
+            OvertimeMode::Draw => OvertimePolicy::Draw,
+            OvertimeMode::Weighted => OvertimePolicy::Weighted(self.overtime_weight),
+        }
+    }
+}
+
+impl ModelArgs {
+    /// The models' settings, which only depend on the options. Nothing is
+    /// fitted to the stored games, as the replays would then be scored with
+    /// what the games they go through taught them.
+    fn config(self) -> StateConfig {
+        let ranker = self.ranker.config();
+        let overtime = self.scoring.overtime();
+        StateConfig {
+            models: self.models,
+            ranker,
+            hist: HistoricalConfig {
+                prior: self.h2h_prior,
+                prior_strength: self.h2h_prior_strength,
+                baseline: self.h2h_baseline,
+                seasons: self.h2h_seasons,
+                meetings: self.h2h_meetings,
+                half_life: self.h2h_half_life,
+                venues: self.h2h_venues,
+                overtime,
+            },
+            overtime,
+            form: FormConfig {
+                window: self.form_window.into(),
+                decay: self.form_decay,
+                overtime,
+            },
+            goals: GoalFormConfig {
+                window: self.goal_window.into(),
+                exponent: self.pythagorean_exponent,
+            },
+            poisson: PoissonConfig {
+                away_rate: self.away_goal_rate,
+                home_rate: self.home_goal_rate,
+                learning_rate: self.strength_rate,
+            },
+            window: self.scoring.window,
+        }
+    }
+}
+
+impl Cli {
+    pub async fn run(self) -> anyhow::Result<()> {
+        match self.command {
+            Command::Plot { data, out } => plot::run(&data, &out),
+            Command::Train { data, samples } => train::run(&data, samples),
+            command => {
+                info!("Fetching database");
+                let db = DataBase::new(&self.db)?;
+                info!("Database started successfully");
+                match command {
+                    Command::Seed => seed::run(&db).await,
+                    Command::Backfill {
+                        from,
+                        to,
+                        data,
+                        in_memory,
+                        snapshot_every,
+                        model,
+                    } => {
+                        if in_memory {
+                            db.load_memory()?;
+                        }
+                        backfill::run(
+                            &db,
+                            from,
+                            to,
+                            data.as_deref(),
+                            snapshot_every,
+                            model.config(),
+                        )
+                        .await
+                    }
+                    Command::Replay {
+                        batch,
+                        in_memory,
+                        reliability,
+                        model,
+                    } => {
+                        if in_memory {
+                            db.load_memory()?;
+                        }
+                        replay::run(&db, batch, model.config(), reliability.as_deref())
+                    }
+                    Command::Backtest { runs, by_season } => backtest::run(&db, &runs, by_season),
+                    Command::Calibrate {
+                        method,
+                        batch,
+                        in_memory,
+                        model,
+                    } => {
+                        if in_memory {
+                            db.load_memory()?;
+                        }
+                        calibrate::run(&db, batch, model.config(), method)
+                    }
+                    Command::Rank { n, system } => rank::run(&db, n, system),
+                    Command::Predict { date, model } => {
+                        predict::run(&db, date, model.config()).await
+                    }
+                    Command::Reset => reset::run(&db),
+                    Command::Plot { .. } | Command::Train { .. } => unreachable!(),
+                }
+            }
+        }
+    }
+}
+
+pub fn print_accuracy<S>(state: &State<'_, S>) {
+    let labels = state.labels();
+    let accs = state.get_accuracy();
+    for (label, (predicted_wins, ngames, acc)) in labels.iter().zip(accs.iter()) {
+        println!(
+            "The {} model predicted {} wins out of {} games with an accuracy of {:.2}%",
+            label,
+            predicted_wins,
+            ngames,
+            acc * 100.
+        )
+    }
+    println!(
+        "\n{:<14}  {:>8}  {:>8}  {:>6}  {:>6}  {:>16}",
+        "model", "accuracy", "log loss", "brier", "rps", "rolling log loss"
+    );
+    for (label, scores) in labels.iter().zip(state.get_metrics()) {
+        println!(
+            "{label:<14}  {:>7.2}%  {:>8.4}  {:>6.4}  {:>6.4}  {:>16.4}",
+            scores.total.accuracy() * 100.,
+            scores.total.log_loss(),
+            scores.total.brier(),
+            scores.total.rps(),
+            scores.rolling().log_loss()
+        );
+    }
+}
+
+/// Predicted against observed win rates of every model's favourites
+pub fn print_calibration<S>(state: &State<'_, S>) {
+    for (label, calibration) in state
+        .labels()
+        .iter()
+        .zip(state.get_calibration(calibration::BINS))
+    {
+        println!(
+            "\nCalibration of the {label} model over {} games, ECE {:.4}",
+            calibration.games, calibration.ece
+        );
+        println!("{:>9}  {:>8}  {:>7}", "predicted", "observed", "games");
+        for bin in &calibration.bins {
+            println!(
+                "{:>8.2}%  {:>7.2}%  {:>7}",
+                bin.predicted * 100.,
+                bin.observed * 100.,
+                bin.games
+            );
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::{fs::OpenOptions, path::Path};
+
+use anyhow::bail;
+use chrono::NaiveDate;
+use csv::StringRecord;
+use log::info;
+
+use crate::{
+    DATA_PATH,
+    backfill::Backfill,
+    cli::{print_accuracy, print_calibration},
+    data::{db::DataBase, models::data::DataPackage},
+    model::state::StateConfig,
+};
+
+/// Header of the scores already in `path`, `None` when there are none yet
+fn existing_header(path: &Path) -> anyhow::Result<Option<StringRecord>> {
+    if !path.exists() || path.metadata()?.len() == 0 {
+        return Ok(None);
+    }
+    Ok(Some(csv::Reader::from_path(path)?.headers()?.clone()))
+}
+
+/// The tracked export if it holds the scores of the same models, or none
+/// yet, and otherwise one beside it named after `columns`, so that changing
+/// the models never appends to another set's scores
+fn default_data(columns: &[&str], header: &[String]) -> anyhow::Result<String> {
+    let tracked = Path::new(DATA_PATH);
+    match existing_header(tracked)? {
+        Some(existing) if existing.iter().ne(header.iter()) => Ok(tracked
+            .with_file_name(format!("metrics_{}.csv", columns.join("_")))
+            .to_string_lossy()
+            .into_owned()),
+        _ => Ok(DATA_PATH.to_string()),
+    }
+}
+
+pub async fn run(
+    db: &DataBase,
+    from: NaiveDate,
+    to: NaiveDate,
+    data: Option<&str>,
+    snapshot_every: usize,
+    config: StateConfig,
+) -> anyhow::Result<()> {
+    let mut backfill = Backfill::new(db, config)?;
+    backfill.snapshot_every = snapshot_every;
+    let columns = backfill.state.columns();
+    let header = DataPackage::header(&columns);
+    let data = match data {
+        Some(data) => data.to_string(),
+        None => default_data(&columns, &header)?,
+    };
+
+    info!("Fetching Dataset from {data}");
+    let existing = existing_header(Path::new(&data))?;
+    let fresh = existing.is_none();
+    if let Some(existing) = existing
+        && existing.iter().ne(header.iter())
+    {
+        bail!(
+            "{data} holds the scores of other models ({}), export to a new file instead",
+            DataPackage::columns(&existing).join(", ")
+        );
+    }
+    let file = OpenOptions::new().create(true).append(true).open(&data)?;
+    let mut ds = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(file);
+    if fresh {
+        ds.write_record(&header)?;
+    }
+    info!("Dataset fetched successfully");
+    // Games before either team was rated carry nothing to learn from
+    let rank = columns.iter().position(|&column| column == "rank");
+    info!("Retrieving daily scores between {from} till {to}");
+    let ngames = backfill
+        .run(from, to, |game, predictions| {
+            let (away_score, home_score) = game.score;
+            let outcome = if away_score > home_score { 1 } else { 0 };
+            let data_pack = DataPackage::new(predictions, outcome);
+            let DataPackage {
+                away_data,
+                home_data,
+            } = &data_pack;
+            if let Some(rank) = rank
+                && [away_data, home_data]
+                    .iter()
+                    .any(|data| data.scores[rank] == 0.5)
+            {
+                return Ok(());
+            }
+            ds.write_record(data_pack.record())?;
+            Ok(())
+        })
+        .await?;
+    info!("Daily scores retrieved! {ngames} processed");
+    ds.flush()?;
+
+    print_accuracy(&backfill.state);
+    print_calibration(&backfill.state);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        data::storage::MemoryStore,
+        model::{registry::ModelKind, state::State},
+    };
+
+    #[test]
+    fn defaults_to_a_file_for_the_models_run() -> anyhow::Result<()> {
+        let columns = |models: &[ModelKind]| {
+            let config = StateConfig {
+                models: models.to_vec(),
+                ..Default::default()
+            };
+            State::with_config(Arc::new(MemoryStore::new()), config).columns()
+        };
+        // The tracked export holds the first three models' scores
+        let first = columns(&[ModelKind::Ranking, ModelKind::Head2Head, ModelKind::Form]);
+        assert_eq!(
+            default_data(&first, &DataPackage::header(&first))?,
+            DATA_PATH
+        );
+        let all = columns(&ModelKind::ALL);
+        let data = default_data(&all, &DataPackage::header(&all))?;
+        assert_ne!(data, DATA_PATH);
+        assert!(data.ends_with(&format!("metrics_{}.csv", all.join("_"))));
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use anyhow::Context;
+use clap::Parser;
+use log::info;
+
+use crate::{
+    cli::{RankerArgs, ScoringArgs},
+    data::db::DataBase,
+    model::{
+        backtest::{Backtest, Run},
+        metrics::Metrics,
+        registry::ModelKind,
+        state::StateConfig,
+    },
+};
+
+/// The options of a single `--run`. Only the ranker runs in a backtest, so
+/// the other models' options are rejected rather than ignored.
+#[derive(Debug, Parser)]
+#[command(no_binary_name = true)]
+struct RunSpec {
+    #[command(flatten)]
+    ranker: RankerArgs,
+    #[command(flatten)]
+    scoring: ScoringArgs,
+}
+
+impl RunSpec {
+    /// Settings of the run, fixed by the options alone so that nothing is
+    /// fitted to the games being backtested
+    fn config(self) -> StateConfig {
+        StateConfig {
+            models: vec![ModelKind::Ranking],
+            ranker: self.ranker.config(),
+            overtime: self.scoring.overtime(),
+            window: self.scoring.window,
+            ..Default::default()
+        }
+    }
+}
+
+/// Reads `system=elo,k-factor=20,no-carry-over` as the matching ranker flags
+fn parse(spec: &str) -> anyhow::Result<StateConfig> {
+    let args = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|opt| !opt.is_empty())
+        .map(|opt| format!("--{opt}"));
+    RunSpec::try_parse_from(args)
+        .map(RunSpec::config)
+        .with_context(|| format!("Invalid run `{spec}`"))
+}
+
+pub fn run(db: &DataBase, specs: &[String], by_season: bool) -> anyhow::Result<()> {
+    let configs = specs
+        .iter()
+        .map(|spec| Ok((spec.as_str(), parse(spec)?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let games = db.get_games()?;
+    info!(
+        "Backtesting {} configurations on {} stored games",
+        configs.len(),
+        games.len()
+    );
+    // Every run rates the teams on its own copy, kept in memory
+    let scratch = configs
+        .iter()
+        .map(|_| {
+            let scratch = db.scratch()?;
+            scratch.load_memory()?;
+            Ok(scratch)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut backtest = Backtest::default();
+    for ((label, config), db) in configs.into_iter().zip(&scratch) {
+        backtest.add(Run::new(label, db, config));
+    }
+    backtest.run(&games)?;
+
+    let width = backtest
+        .runs
+        .iter()
+        .map(|run| run.label.len())
+        .max()
+        .unwrap_or_default()
+        .max("configuration".len());
+    print_header(width);
+    for run in &backtest.runs {
+        print_row(&run.label, width, &run.scores.total);
+    }
+    if by_season {
+        for season in backtest.seasons() {
+            println!("\n{}-{}", season / 10000, season % 10000);
+            print_header(width);
+            for run in &backtest.runs {
+                if let Some(metrics) = run.scores.by_season.get(&season) {
+                    print_row(&run.label, width, metrics);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_header(width: usize) {
+    println!(
+        "{:<width$}  {:>6}  {:>8}  {:>8}  {:>6}  {:>6}",
+        "configuration", "games", "accuracy", "log loss", "brier", "rps"
+    );
+}
+
+fn print_row(label: &str, width: usize, metrics: &Metrics) {
+    println!(
+        "{label:<width$}  {:>6}  {:>7.2}%  {:>8.4}  {:>6.4}  {:>6.4}",
+        metrics.games,
+        metrics.accuracy() * 100.,
+        metrics.log_loss(),
+        metrics.brier(),
+        metrics.rps()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rating::{overtime::OvertimePolicy, system::RatingSystem};
+
+    #[test]
+    fn runs_take_ranker_options_only() -> anyhow::Result<()> {
+        let config = parse("system=elo, k-factor=20,home-advantage=30,overtime=draw")?;
+        assert_eq!(config.models, [ModelKind::Ranking]);
+        assert_eq!(config.ranker.system, RatingSystem::Elo);
+        assert_eq!(config.ranker.params.elo.k, 20.);
+        assert_eq!(config.ranker.home_advantage, 30.);
+        assert_eq!(config.overtime, OvertimePolicy::Draw);
+        // Left out, the home advantage is a constant rather than a fit
+        assert!(parse("system=elo")?.ranker.home_advantage > 0.);
+        for spec in ["models=ranking,h2h", "h2h-meetings=2", "form-window=5"] {
+            assert!(parse(spec).is_err(), "{spec} was accepted");
+        }
+        Ok(())
+    }
+}
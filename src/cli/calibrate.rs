@@ -0,0 +1,87 @@
+use log::info;
+use skillratings::Outcomes;
+
+use crate::{
+    data::{db::DataBase, models::prediction::Prediction},
+    model::{
+        calibrator::{CalibrationMethod, Calibrator},
+        metrics::{Metrics, actual_score},
+        state::{State, StateConfig},
+    },
+    utils::outcome_from_prob,
+};
+
+/// Share of the latest games held out of the fit to report the log loss on
+const HOLDOUT: f64 = 0.2;
+
+/// Log loss of the predictions in `samples`, passed through `calibrator` if
+/// given
+fn log_loss(samples: &[(f64, Outcomes)], calibrator: Option<&Calibrator>) -> f64 {
+    let mut metrics = Metrics::default();
+    for &(exp, outcome) in samples {
+        let prediction = Prediction {
+            exp_away: exp,
+            exp_home: 1. - exp,
+            outcome: outcome_from_prob(exp, 1. - exp),
+        };
+        let prediction = match calibrator {
+            Some(calibrator) => calibrator.calibrate(prediction),
+            None => prediction,
+        };
+        metrics.record(&prediction, outcome);
+    }
+    metrics.log_loss()
+}
+
+pub fn run(
+    db: &DataBase,
+    batch_size: usize,
+    config: StateConfig,
+    method: CalibrationMethod,
+) -> anyhow::Result<()> {
+    let mut state = State::with_config(db, config);
+    info!("Replaying stored games to collect the models' predictions");
+    let ngames = state.replay(batch_size)?;
+    db.unload_memory()?;
+    info!("Fitting calibrators on {ngames} games");
+    info!(
+        "Log losses are of the latest {:.0}% of games, calibrated by a fit to the others",
+        HOLDOUT * 100.
+    );
+    println!(
+        "{:<14}  {:>15}  {:>14}",
+        "model", "log loss before", "log loss after"
+    );
+    let keys = state.calibrator_keys();
+    let settings = state.calibrator_settings();
+    for (((label, key), settings), samples) in state
+        .labels()
+        .iter()
+        .zip(&keys)
+        .zip(&settings)
+        .zip(state.get_samples())
+    {
+        if samples.is_empty() {
+            continue;
+        }
+        let pairs = samples
+            .iter()
+            .map(|&(exp, outcome)| (exp, actual_score(outcome)))
+            .collect::<Vec<_>>();
+        // The report fits on the earlier games and scores the latest ones,
+        // the calibrator stored is then fitted to all of them
+        let held_out = ((samples.len() as f64 * HOLDOUT).ceil() as usize).min(samples.len() - 1);
+        let split = samples.len() - held_out;
+        if held_out > 0 {
+            let calibrator = Calibrator::fit(method, &pairs[..split]);
+            println!(
+                "{label:<14}  {:>15.4}  {:>14.4}",
+                log_loss(&samples[split..], None),
+                log_loss(&samples[split..], Some(&calibrator))
+            );
+        }
+        let calibrator = Calibrator::fit(method, &pairs);
+        db.set_calibrator(key, &calibrator, samples.len(), settings)?;
+    }
+    Ok(())
+}
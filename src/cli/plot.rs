@@ -0,0 +1,136 @@
+use anyhow::Context;
+use itertools::{Either, Itertools};
+use log::info;
+use plotters::{
+    prelude::*,
+    style::full_palette::{BLUE_400, GREEN_800, ORANGE_700, RED_400, RED_900},
+};
+
+use crate::{
+    data::models::data::{Data, DataPackage},
+    model::calibration::Calibration,
+};
+
+pub fn run(data_path: &str, out: &str) -> anyhow::Result<()> {
+    let (columns, rows) = DataPackage::read_all(data_path)?;
+    let axis = |name: &str| {
+        columns
+            .iter()
+            .position(|column| column == name)
+            .with_context(|| format!("{data_path} has no {name} scores to plot"))
+    };
+    let axes = (axis("rank")?, axis("hist")?, axis("la10")?);
+
+    let (wins, losses): (Vec<_>, Vec<_>) = rows
+        .into_iter()
+        .map(|package| package.away_data)
+        .partition_map(|data| {
+            if data.outcome == 1 {
+                Either::Left(data)
+            } else {
+                Either::Right(data)
+            }
+        });
+
+    info!("Drawing {} wins and {} losses", wins.len(), losses.len());
+    let root = BitMapBackend::gif(out, (640, 480), 50)?.into_drawing_area();
+    for pitch in 0..157 {
+        root.fill(&WHITE)?;
+        let mut chart = ChartBuilder::on(&root)
+            .caption(
+                "Skill Rating - Historical Performance - Last 10 Games",
+                ("sans-serif", 25).into_font(),
+            )
+            .margin(5)
+            .x_label_area_size(30)
+            .y_label_area_size(30)
+            .build_cartesian_3d(0.0..1., 0.0..1., 0.0..1.)?;
+        chart.with_projection(|mut p| {
+            p.yaw = 1.57 - (1.57 - pitch as f64 / 50.0).abs();
+            p.pitch = 1.57 - (1.57 - pitch as f64 / 50.0).abs();
+            p.scale = 0.7;
+            p.into_matrix() // build the projection matrix
+        });
+
+        chart.configure_axes().draw()?;
+
+        let series = [
+            (&wins, &RED_900, &RED_400, "wins"),
+            (&losses, &BLUE, &BLUE_400, "losses"),
+        ];
+        for (datas, colour1, colour2, label) in series {
+            chart
+                .draw_series(datas.iter().map(|Data { scores, .. }| {
+                    let (x, y, z) = axes;
+                    EmptyElement::at((scores[x], scores[y], scores[z]))
+                        + Circle::new((0, 0), 1, ShapeStyle::from(colour1).filled())
+                        + Circle::new((0, 0), 0.5, ShapeStyle::from(colour2).filled())
+                }))?
+                .label(label)
+                .legend(|(x, y)| Circle::new((x, y), 3, ShapeStyle::from(*colour2).filled()));
+        }
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperRight)
+            .margin(20)
+            .legend_area_size(5)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+
+        root.present()?;
+    }
+    root.present()?;
+    info!("Plot saved to {out}");
+    Ok(())
+}
+
+/// Draws each model's observed win rate against the probability it gave its
+/// favourites, perfectly calibrated models sitting on the diagonal
+pub fn reliability(models: &[(&str, &Calibration)], out: &str) -> anyhow::Result<()> {
+    let root = BitMapBackend::new(out, (640, 480)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption("Reliability", ("sans-serif", 25).into_font())
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d(0.5..1., 0.0..1.)?;
+    chart
+        .configure_mesh()
+        .x_desc("Predicted win probability of the favourite")
+        .y_desc("Observed win rate")
+        .draw()?;
+    chart.draw_series(DashedLineSeries::new(
+        [(0.5, 0.5), (1., 1.)],
+        5,
+        5,
+        BLACK.into(),
+    ))?;
+    for (i, (label, calibration)) in models.iter().enumerate() {
+        let colour = [RED_900, BLUE, GREEN_800, ORANGE_700][i % 4];
+        let points = calibration
+            .bins
+            .iter()
+            .map(|bin| (bin.predicted, bin.observed))
+            .collect::<Vec<_>>();
+        chart.draw_series(
+            points
+                .iter()
+                .map(|&point| Circle::new(point, 3, colour.filled())),
+        )?;
+        chart
+            .draw_series(LineSeries::new(points, colour))?
+            .label(format!("{label} (ECE {:.3})", calibration.ece))
+            .legend(move |(x, y)| PathElement::new([(x, y), (x + 20, y)], colour));
+    }
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    root.present()?;
+    info!("Reliability diagram saved to {out}");
+    Ok(())
+}
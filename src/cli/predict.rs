@@ -0,0 +1,213 @@
+use chrono::NaiveDate;
+use itertools::Itertools;
+use log::info;
+use nhl_api::{Client, GameDate};
+
+use crate::{
+    data::db::DataBase,
+    model::{
+        calibrator::Calibrated,
+        historical::{HistoricalConfig, HistoricalMatchupModel},
+        model::Model,
+        poisson::PoissonModel,
+        ranker::{self, RankingModel},
+        state::StateConfig,
+    },
+};
+
+/// The models `predict` runs, with the calibrators `calibrate` fitted to the
+/// same settings
+struct Models<'a> {
+    ranker: Calibrated<RankingModel<&'a DataBase>>,
+    hist: Calibrated<HistoricalMatchupModel<&'a DataBase>>,
+    poisson: PoissonModel<&'a DataBase>,
+}
+
+impl<'a> Models<'a> {
+    fn new(db: &'a DataBase, config: StateConfig) -> rusqlite::Result<Self> {
+        Ok(Self {
+            ranker: Calibrated::stored(db, RankingModel::with_config(db, config.ranker))?,
+            hist: Calibrated::stored(
+                db,
+                HistoricalMatchupModel::with_config(
+                    db,
+                    HistoricalConfig {
+                        overtime: config.overtime,
+                        ..config.hist
+                    },
+                ),
+            )?,
+            poisson: PoissonModel::with_config(db, config.poisson),
+        })
+    }
+}
+
+pub async fn run(
+    db: &DataBase,
+    date: Option<NaiveDate>,
+    config: StateConfig,
+) -> anyhow::Result<()> {
+    let client = Client::new()?;
+    let system = config.ranker.system;
+    let Models {
+        ranker,
+        hist,
+        poisson,
+    } = Models::new(db, config)?;
+
+    info!("Let's pick a winner for today");
+    let sched = client.daily_schedule(date.map(GameDate::Date)).await?;
+    info!("Found {} games", sched.games.len());
+    let mut winners_rank = vec![];
+    let mut winners_freq = vec![];
+    let mut scorelines = vec![];
+    for game in &sched.games {
+        let away = &game.away_team;
+        let home = &game.home_team;
+        info!(
+            "Game with id: {} will be played between {} @ {}",
+            game.id, away.abbrev, home.abbrev,
+        );
+        let away_team = db.get_team(away.id)?;
+        let home_team = db.get_team(home.id)?;
+        info!(
+            "The {} are rated: {}",
+            &away_team.name,
+            db.get_rating(away.id, system)?.points()
+        );
+        info!(
+            "The {} are rated: {}",
+            &home_team.name,
+            db.get_rating(home.id, system)?.points()
+        );
+        let rank = ranker.predict(away.id, home.id)?;
+        // The head to head leans on the ranker's own odds, as in a replay
+        let earlier = [(ranker::LABEL, ranker.model.predict(away.id, home.id)?)];
+        let prior = hist.model.prior(&earlier);
+        let (.., freq) = hist
+            .model
+            .predict_with_prior(away.id, home.id, prior, None)?;
+        let freq = hist.calibrate(freq);
+        let meetings = hist.model.record(away.id, home.id, None)?.meetings;
+        if rank.exp_away > rank.exp_home {
+            info!(
+                "{} is expected to win with probability {} by ranking",
+                &away_team.name, rank.exp_away
+            );
+            winners_rank.push((away_team.name.clone(), rank.exp_away));
+        } else {
+            info!(
+                "{} is expected to win with probability {} by ranking",
+                &home_team.name, rank.exp_home
+            );
+            winners_rank.push((home_team.name.clone(), rank.exp_home))
+        }
+        if freq.exp_away > freq.exp_home {
+            info!(
+                "{} is expected to win with probability {} by history",
+                &away_team.name, freq.exp_away
+            );
+            winners_freq.push((away_team.name.clone(), freq.exp_away, meetings));
+        } else {
+            info!(
+                "{} is expected to win with probability {} by history",
+                &home_team.name, freq.exp_home
+            );
+            winners_freq.push((home_team.name.clone(), freq.exp_home, meetings))
+        }
+        let scoreline = poisson.scoreline(away.id, home.id)?;
+        let likeliest = scoreline
+            .most_likely(3)
+            .into_iter()
+            .map(|((away_goals, home_goals), p)| {
+                format!("{away_goals}-{home_goals} {:.1}%", p * 100.)
+            })
+            .join(", ");
+        scorelines.push(format!(
+            "{} @ {}: {:.2}-{:.2} expected, {:.1}% past regulation, likeliest {likeliest}",
+            away.abbrev,
+            home.abbrev,
+            scoreline.rates.0,
+            scoreline.rates.1,
+            scoreline.overtime * 100.
+        ));
+    }
+    winners_rank.sort_by(|(_, exp1), (_, exp2)| exp2.total_cmp(exp1));
+    info!("Here are the expected winners for tonight's games by rank:");
+    for (team, exp) in winners_rank {
+        println!("{} {:.2}%", team, exp * 100.);
+    }
+    winners_freq.sort_by(|(_, f1, _), (_, f2, _)| f2.total_cmp(f1));
+    info!("Here are the expected winners for tonight's games by history:");
+    for (team, f, meetings) in winners_freq {
+        println!("{} {:.2}% over {} meetings", team, f * 100., meetings);
+    }
+    info!("Here are the likeliest regulation scores of tonight's games:");
+    for scoreline in scorelines {
+        println!("{scoreline}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use nhl_api::{GameType, PeriodType};
+
+    use super::*;
+    use crate::{
+        cli::{ModelArgs, calibrate},
+        data::models::games::Game,
+        model::calibrator::CalibrationMethod,
+    };
+
+    #[derive(Debug, Parser)]
+    #[command(no_binary_name = true)]
+    struct Flags {
+        #[command(flatten)]
+        model: ModelArgs,
+    }
+
+    #[test]
+    fn applies_what_calibrate_fitted() -> anyhow::Result<()> {
+        let db = DataBase::new(":memory:")?;
+        for (id, name, abbrev) in [
+            (8, "Montréal Canadiens", "MTL"),
+            (10, "Toronto Maple Leafs", "TOR"),
+        ] {
+            db.add_team(id, name.to_string(), abbrev.to_string())?;
+            db.add_last10(id)?;
+        }
+        let (mtl, tor) = (db.get_team(8)?, db.get_team(10)?);
+        db.add_h2h(&mtl.vs(&tor))?;
+        db.add_h2h(&tor.vs(&mtl))?;
+        for (i, score) in [(4, 1), (2, 3), (5, 2), (1, 3), (3, 2)]
+            .into_iter()
+            .enumerate()
+        {
+            db.add_game(&Game {
+                id: 2023020001 + i as i64,
+                date: NaiveDate::from_ymd_opt(2023, 10, 10 + i as u32).unwrap(),
+                season: 20232024,
+                game_type: GameType::RegularSeason,
+                away_id: 8,
+                home_id: 10,
+                score,
+                resolution: PeriodType::Regulation,
+            })?;
+        }
+        let flags = ["--beta", "4", "--h2h-prior", "ranking"];
+        let config = Flags::try_parse_from(flags)?.model.config();
+        calibrate::run(&db, 10, config, CalibrationMethod::Isotonic)?;
+
+        // The replay moved the ratings, the settings still match
+        let models = Models::new(&db, Flags::try_parse_from(flags)?.model.config())?;
+        assert!(models.ranker.calibrator.is_some());
+        assert!(models.hist.calibrator.is_some());
+        let other = Flags::try_parse_from(["--beta", "5", "--h2h-prior", "ranking"])?;
+        let models = Models::new(&db, other.model.config())?;
+        assert!(models.ranker.calibrator.is_none());
+        assert!(models.hist.calibrator.is_some());
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use log::info;
+
+use crate::{
+    data::db::DataBase,
+    model::{
+        historical::HistoricalMatchupModel,
+        poisson::PoissonModel,
+        ranker::{RankerConfig, RankingModel},
+    },
+    rating::system::RatingSystem,
+};
+
+pub fn run(db: &DataBase, n: u64, system: RatingSystem) -> anyhow::Result<()> {
+    info!("Querying the top and bottom {n} teams in the league");
+    let teams = db.get_ratings(system)?;
+    let n = (n as usize).min(teams.len());
+    println!("\nHere are the top {n} teams in the league");
+    for (i, (team, rating)) in teams.iter().enumerate().take(n) {
+        println!("{} - {} ({})", i + 1, team.name, rating.points());
+    }
+    println!("\nHere are the bottom {n} teams in the league");
+    let total = teams.len();
+    for (i, (team, rating)) in teams.iter().rev().enumerate().take(n) {
+        println!("{} - {} ({})", total - i, team.name, rating.points());
+    }
+    // What the stored games suggest for the models' options, which are never
+    // fitted on their own since a replay would then learn from the games it
+    // scores
+    if let Some(offset) = RankingModel::fit_home_advantage(
+        db,
+        &RankerConfig {
+            system,
+            ..Default::default()
+        },
+    )? {
+        let (ngames, home_score) = db.home_record()?;
+        println!(
+            "\nHome ice is worth {offset:.2} rating points, home teams won {:.2}% of {ngames} games",
+            home_score * 100.
+        );
+    }
+    if let (Some(baseline), Some((away_rate, home_rate))) = (
+        HistoricalMatchupModel::fit_baseline(db)?,
+        PoissonModel::fit_rates(db)?,
+    ) {
+        println!(
+            "Away teams took {:.2}% of the results and scored {away_rate:.2} goals to {home_rate:.2} in regulation",
+            baseline * 100.
+        );
+        println!(
+            "To use them pass --h2h-baseline {baseline:.3} --away-goal-rate {away_rate:.3} --home-goal-rate {home_rate:.3}"
+        );
+    }
+    Ok(())
+}
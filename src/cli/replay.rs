@@ -0,0 +1,35 @@
+use log::info;
+
+use crate::{
+    cli::{plot, print_accuracy, print_calibration},
+    data::db::DataBase,
+    model::{
+        calibration,
+        state::{State, StateConfig},
+    },
+};
+
+pub fn run(
+    db: &DataBase,
+    batch_size: usize,
+    config: StateConfig,
+    reliability: Option<&str>,
+) -> anyhow::Result<()> {
+    let mut state = State::with_config(db, config);
+    info!("Replaying stored games");
+    let ngames = state.replay(batch_size)?;
+    db.unload_memory()?;
+    info!("Replayed {ngames} games");
+    print_accuracy(&state);
+    print_calibration(&state);
+    if let Some(out) = reliability {
+        let calibrations = state.get_calibration(calibration::BINS);
+        let models = state
+            .labels()
+            .into_iter()
+            .zip(&calibrations)
+            .collect::<Vec<_>>();
+        plot::reliability(&models, out)?;
+    }
+    Ok(())
+}
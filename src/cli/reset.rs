@@ -0,0 +1,10 @@
+use log::info;
+
+use crate::data::db::DataBase;
+
+pub fn run(db: &DataBase) -> anyhow::Result<()> {
+    info!("Deleting for reuse");
+    db.clear()?;
+    info!("Succesfully deleted tables");
+    Ok(())
+}
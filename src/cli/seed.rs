@@ -0,0 +1,67 @@
+use log::info;
+
+use std::collections::HashMap;
+
+use crate::data::{
+    db::DataBase,
+    models::{
+        franchises::{TeamEra, known_eras},
+        teams::TeamsResponse,
+    },
+};
+
+const TEAMS_URL: &str = "https://api.nhle.com/stats/rest/en/team";
+
+pub async fn run(db: &DataBase) -> anyhow::Result<()> {
+    let http = reqwest::Client::new();
+
+    info!("Adding Teams");
+    let response = http
+        .get(TEAMS_URL)
+        .send()
+        .await?
+        .json::<TeamsResponse>()
+        .await?;
+    for team in &response.teams {
+        db.add_team(team.id, team.full_name.clone(), team.tri_code.clone())?;
+        db.add_last10(team.id)?;
+    }
+    info!("Teams added succesfully");
+
+    info!("Adding franchise registry");
+    let franchises = response
+        .teams
+        .iter()
+        .map(|team| (team.id, team.franchise_id))
+        .collect::<HashMap<_, _>>();
+    for team in &response.teams {
+        db.add_team_era(&TeamEra {
+            team_id: team.id,
+            franchise_id: team.franchise_id,
+            name: team.full_name.clone(),
+            abbrev: team.tri_code.clone(),
+            valid_from: None,
+            valid_to: None,
+        })?;
+    }
+    for era in known_eras(|id| franchises.get(&id).copied().flatten()) {
+        db.add_team_era(&era)?;
+    }
+    info!("Franchise registry added succesfully");
+
+    info!("Adding Head2Heads");
+    for team1 in &response.teams {
+        let id1 = team1.id;
+        for team2 in &response.teams {
+            let id2 = team2.id;
+            if id1 != id2 {
+                let db_team1 = db.get_team(id1)?;
+                let db_team2 = db.get_team(id2)?;
+                db.add_h2h(&db_team1.vs(&db_team2))?;
+                db.add_h2h(&db_team2.vs(&db_team1))?;
+            }
+        }
+    }
+    info!("Head2Heads added succesfully");
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use linfa::prelude::*;
+use linfa_bayes::GaussianNbParams;
+use linfa_logistic::LogisticRegression;
+use linfa_trees::{DecisionTree, SplitQuality};
+use log::{debug, info};
+use ndarray::{Array1, Array2};
+
+use crate::data::models::data::DataPackage;
+
+pub fn run(data_path: &str, num_samples: usize) -> anyhow::Result<()> {
+    info!("Fetching dataset for training");
+
+    let (columns, rows) = DataPackage::read_all(data_path)?;
+    let mut records = vec![];
+    let mut targets = vec![];
+    for DataPackage {
+        away_data,
+        home_data,
+    } in &rows
+    {
+        for (away, home) in away_data.scores.iter().zip(&home_data.scores) {
+            records.push(*away);
+            records.push(*home);
+        }
+        targets.push(away_data.outcome as usize);
+    }
+    let records = Array2::from_shape_vec((rows.len(), 2 * columns.len()), records)?;
+    let targets = Array1::from_vec(targets);
+    let dataset = DatasetBase::new(records, targets)
+        .with_feature_names(
+            columns
+                .iter()
+                .flat_map(|column| [format!("Away {column}"), format!("Home {column}")])
+                .collect(),
+        )
+        .with_target_names(vec!["Outcome"]);
+    info!("Dataset fetched. Time to learn!");
+
+    info!("Constructing Decision Tree");
+    let tree_params = DecisionTree::params().split_quality(SplitQuality::Entropy);
+
+    info!("Tree constructed. Splitting dataset.");
+    let mut rng = rand::thread_rng();
+    let mut boot = dataset.bootstrap_samples(num_samples, &mut rng);
+    let train = boot.next().unwrap();
+    let val = boot.next().unwrap();
+    info!("Learning");
+    let tree = tree_params.fit(&train)?;
+    let logist = LogisticRegression::default().fit(&train)?;
+    let bayes = GaussianNbParams::new().fit(&train)?;
+    debug!("Trying Random Forest");
+    let random_forest = boot
+        .take(10)
+        .flat_map(|booted_data| tree_params.fit(&booted_data))
+        .collect::<Vec<_>>();
+    let mut predicted = Array1::from_vec(vec![(0, 0); val.targets.len()]);
+    debug!("Random Forest done!");
+    for t in random_forest {
+        let t_predicted = t.predict(&val);
+        predicted
+            .iter_mut()
+            .zip(t_predicted.iter())
+            .for_each(|((good, bad), pred)| if *pred == 1 { *good += 1 } else { *bad += 1 })
+    }
+    let predicted = predicted.mapv(|(good, bad)| if good > bad { 1 } else { 0 });
+
+    info!("Time to predict!");
+    let confusion_matrix: ConfusionMatrix<usize> = predicted.confusion_matrix(&val)?;
+    report("Random Forest", &confusion_matrix);
+    let confusion_matrix = tree.predict(&val).confusion_matrix(&val)?;
+    report("Decision Tree", &confusion_matrix);
+    let confusion_matrix = logist.predict(&val).confusion_matrix(&val)?;
+    report("Logistic Regression", &confusion_matrix);
+    let confusion_matrix = bayes.predict(&val).confusion_matrix(&val)?;
+    report("Naive Bayes", &confusion_matrix);
+    Ok(())
+}
+
+fn report(label: &str, confusion_matrix: &ConfusionMatrix<usize>) {
+    println!(
+        "Learning completed for {}:\n\tRecall:\t\t{}\n\tAccuracy:\t {}\n\tPrecision:\t{}\n{:?}",
+        label,
+        confusion_matrix.recall(),
+        confusion_matrix.accuracy(),
+        confusion_matrix.precision(),
+        confusion_matrix
+    );
+}
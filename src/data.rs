@@ -1,2 +1,5 @@
 pub mod db;
+pub mod memory;
+pub mod migrations;
 pub mod models;
+pub mod storage;
@@ -1,120 +1,453 @@
-use crate::data::models::{head2head::Head2Head, last10::Last10, teams::Team};
-use rusqlite::{Connection, Result, params};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::{
+    data::{
+        memory::MemoryState,
+        migrations,
+        models::{
+            franchises::TeamEra, games::Game, goals::GoalForm, head2head::Head2Head,
+            last10::Last10, strength::Strength, teams::Team,
+        },
+    },
+    model::calibrator::Calibrator,
+    rating::system::{Rating, RatingSystem},
+};
+use chrono::NaiveDate;
+use log::debug;
+use rusqlite::{Connection, Error::QueryReturnedNoRows, OptionalExtension, Result, params};
 use skillratings::{Outcomes, weng_lin::WengLinRating};
 
 pub type TeamID = i64;
 
+/// A transaction begun by [`DataBase::transaction`], rolled back when dropped
+/// before being committed
+struct Pending<'a>(&'a DataBase, bool);
+
+impl Pending<'_> {
+    fn commit(mut self) -> Result<()> {
+        self.0.conn().execute_batch("COMMIT;")?;
+        self.1 = true;
+        Ok(())
+    }
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        if !self.1 {
+            let _ = self.0.conn().execute_batch("ROLLBACK;");
+        }
+    }
+}
+
+/// The SQLite connection, plus the model state when running in memory mode
 #[derive(Debug)]
-pub struct DataBase(Connection);
+pub struct DataBase(Mutex<Connection>, Mutex<Option<MemoryState>>);
 
 impl DataBase {
-    pub fn new(path: &str) -> Result<DataBase> {
-        let conn = Connection::open(path)?;
-        conn.execute_batch(
-            "
-        CREATE TABLE IF NOT EXISTS teams (
-            id INTEGER PRIMARY KEY,
-            name TEXT NOT NULL,
-            abbreviation TEXT NOT NULL,
-            rating REAL DEFAULT 25.0,
-            uncertainty REAL DEFAULT 8.33
-        );
-        CREATE TABLE IF NOT EXISTS H2H (
-            awayID INTEGER,
-            homeID INTEGER,
-            totalGames INTEGER DEFAULT 0,
-            teamWins INTEGER DEFAULT 0,
-            teamWinFreq REAL DEFAULT 0.0,
-            PRIMARY KEY (awayID, homeID)
-        );
-        CREATE TABLE IF NOT EXISTS last10 (
-            id INTEGER PRIMARY KEY,
-            wins INTEGER DEFAULT 0,
-            losses INTEGER DEFAULT 0,
-            games INTEGER DEFAULT 0
+    /// Opens the database at `path`, upgrading its schema to the latest version
+    pub fn new(path: &str) -> anyhow::Result<DataBase> {
+        let mut conn = Connection::open(path)?;
+        migrations::migrate(&mut conn)?;
+        conn.set_prepared_statement_cache_capacity(32);
+        Ok(DataBase(Mutex::new(conn), Mutex::new(None)))
+    }
+
+    /// An in-memory database holding the same teams and franchise registry,
+    /// with fresh ratings and without any games. Models can be run on it
+    /// without touching this one.
+    pub fn scratch(&self) -> anyhow::Result<DataBase> {
+        let scratch = DataBase::new(":memory:")?;
+        scratch.transaction(|| -> Result<()> {
+            for id in self.team_ids()? {
+                let team = self.get_team(id)?;
+                scratch.add_team(id, team.name, team.abbrev)?;
+                for era in self.get_team_eras(id)? {
+                    scratch.add_team_era(&era)?;
+                }
+            }
+            Ok(())
+        })?;
+        Ok(scratch)
+    }
+
+    /// Runs `f` inside a transaction, committing only if it succeeds. When a
+    /// transaction is already open `f` simply joins it, so batches can wrap
+    /// calls that are transactional on their own.
+    pub fn transaction<T, E>(
+        &self,
+        f: impl FnOnce() -> std::result::Result<T, E>,
+    ) -> std::result::Result<T, E>
+    where
+        E: From<rusqlite::Error>,
+    {
+        if !self.conn().is_autocommit() {
+            return f();
+        }
+        self.conn().execute_batch("BEGIN DEFERRED;")?;
+        let pending = Pending(self, false);
+        let value = f()?;
+        pending.commit()?;
+        Ok(value)
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn memory(&self) -> MutexGuard<'_, Option<MemoryState>> {
+        self.1.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Loads the ratings, head to heads and last 10 games into memory. Until
+    /// the next [`snapshot`](Self::snapshot) the models read and write there,
+    /// and processed games and checkpoints are held back with them.
+    pub fn load_memory(&self) -> Result<()> {
+        let memory = self.read_memory()?;
+        debug!(
+            "Loaded {} teams and {} head to heads into memory",
+            memory.teams.len(),
+            memory.h2h.len()
         );
-        ",
-        )?;
-        Ok(DataBase(conn))
+        *self.memory() = Some(memory);
+        Ok(())
+    }
+
+    fn read_memory(&self) -> Result<MemoryState> {
+        let conn = self.conn();
+        let mut memory = MemoryState::default();
+        let mut stmnt = conn.prepare("SELECT * FROM teams;")?;
+        for team in stmnt.query_map([], |row| Team::try_from(row))? {
+            let team = team?;
+            memory.teams.insert(team.id.into(), team);
+        }
+        let mut stmnt = conn.prepare("SELECT * FROM H2H;")?;
+        for h2h in stmnt.query_map([], |row| Head2Head::try_from(row))? {
+            let h2h = h2h?;
+            memory.h2h.insert((h2h.team1.into(), h2h.team2.into()), h2h);
+        }
+        let mut stmnt = conn.prepare("SELECT * FROM last10;")?;
+        for last10 in stmnt.query_map([], |row| Last10::try_from(row))? {
+            let last10 = last10?;
+            memory.last10.insert(last10.id.into(), last10);
+        }
+        let mut stmnt = conn.prepare("SELECT teamID, rating FROM ratings;")?;
+        for row in stmnt.query_map([], |row| Ok((row.get(0)?, rating_from_json(row, 1)?)))? {
+            let (id, rating): (TeamID, Rating) = row?;
+            memory.ratings.insert((id, rating.system()), rating);
+        }
+        let mut stmnt = conn.prepare("SELECT teamID, games FROM goal_form;")?;
+        for form in stmnt.query_map([], goal_form_from_row)? {
+            let form = form?;
+            memory.goal_form.insert(form.id, form);
+        }
+        let mut stmnt = conn.prepare("SELECT teamID, attack, defence FROM strengths;")?;
+        for strength in stmnt.query_map([], strength_from_row)? {
+            let strength = strength?;
+            memory.strengths.insert(strength.id, strength);
+        }
+        Ok(memory)
+    }
+
+    pub fn in_memory(&self) -> bool {
+        self.memory().is_some()
+    }
+
+    /// Writes the in-memory state to SQLite in a single transaction, keeping
+    /// memory mode on. Does nothing outside memory mode.
+    pub fn snapshot(&self) -> Result<()> {
+        {
+            let guard = self.memory();
+            let Some(memory) = guard.as_ref() else {
+                return Ok(());
+            };
+            self.transaction(|| -> Result<()> {
+                for team in memory.teams.values() {
+                    self.write_team_rating(team.id.into(), team.rating)?;
+                }
+                for h2h in memory.h2h.values() {
+                    self.write_h2h(h2h)?;
+                }
+                for last10 in memory.last10.values() {
+                    self.write_last10(last10)?;
+                }
+                for (&(id, _), rating) in &memory.ratings {
+                    self.write_rating(id, rating)?;
+                }
+                for form in memory.goal_form.values() {
+                    self.write_goal_form(form)?;
+                }
+                for strength in memory.strengths.values() {
+                    self.write_strength(strength)?;
+                }
+                for &game_id in &memory.processed {
+                    self.write_processed(game_id)?;
+                }
+                if let Some(date) = memory.checkpoint {
+                    self.write_checkpoint(date)?;
+                }
+                Ok(())
+            })?;
+        }
+        if let Some(memory) = self.memory().as_mut() {
+            memory.processed.clear();
+        }
+        Ok(())
+    }
+
+    /// Takes a last snapshot and goes back to reading and writing SQLite
+    pub fn unload_memory(&self) -> Result<()> {
+        self.snapshot()?;
+        *self.memory() = None;
+        Ok(())
     }
 
     pub fn clear(&self) -> Result<()> {
-        self.0.execute_batch(
+        *self.memory() = None;
+        self.conn().execute_batch(
             "
             DROP TABLE IF EXISTS teams;
             DROP TABLE IF EXISTS H2H;
             DROP TABLE IF EXISTS last10;
+            DROP TABLE IF EXISTS checkpoint;
+            DROP TABLE IF EXISTS processed;
+            DROP TABLE IF EXISTS games;
+            DROP TABLE IF EXISTS team_history;
+            DROP TABLE IF EXISTS ratings;
+            DROP TABLE IF EXISTS calibrators;
+            DROP TABLE IF EXISTS goal_form;
+            DROP TABLE IF EXISTS strengths;
+            PRAGMA user_version = 0;
         ",
         )
     }
 
+    /// Puts every rating, head to head record and last 10 games back to their
+    /// defaults and forgets which games were processed. Stored games are kept.
+    pub fn reset_state(&self) -> Result<()> {
+        let start = WengLinRating::new();
+        self.conn()
+            .prepare_cached("UPDATE teams SET rating = ?1, uncertainty = ?2;")?
+            .execute(params![start.rating, start.uncertainty])?;
+        self.conn().execute_batch(
+            "
+            UPDATE H2H SET totalGames = 0, teamWins = 0, teamWinFreq = 0.0, otLosses = 0, score = 0.0;
+            UPDATE last10 SET wins = 0, losses = 0, games = 0, otLosses = 0, overtime = 0, ties = 0, played = 0;
+            DELETE FROM processed;
+            DELETE FROM ratings;
+            DELETE FROM goal_form;
+            DELETE FROM strengths;
+        ",
+        )?;
+        if self.in_memory() {
+            self.load_memory()?;
+        }
+        Ok(())
+    }
+
     pub fn add_team(&self, id: impl Into<TeamID>, name: String, abbrev: String) -> Result<()> {
         let id = id.into();
-        let conn = &self.0;
-        conn.execute(
-            "INSERT OR IGNORE INTO teams (id, name, abbreviation) VALUES (?1, ?2, ?3);",
-            params![id, name, abbrev],
-        )?;
+        let start = WengLinRating::new();
+        let conn = self.conn();
+        conn.prepare_cached(
+            "
+        INSERT OR IGNORE INTO teams (id, name, abbreviation, rating, uncertainty)
+        VALUES (?1, ?2, ?3, ?4, ?5);
+        ",
+        )?
+        .execute(params![id, name, abbrev, start.rating, start.uncertainty])?;
         Ok(())
     }
 
     pub fn get_team(&self, id: impl Into<TeamID>) -> Result<Team> {
         let id = id.into();
-        let conn = &self.0;
-        let team = conn.query_row("SELECT * FROM teams WHERE id = ?1;", params![id], |row| {
-            Team::try_from(row)
-        })?;
+        if let Some(memory) = self.memory().as_ref() {
+            return memory.teams.get(&id).cloned().ok_or(QueryReturnedNoRows);
+        }
+        let conn = self.conn();
+        let team = conn
+            .prepare_cached("SELECT * FROM teams WHERE id = ?1;")?
+            .query_row(params![id], |row| Team::try_from(row))?;
         Ok(team)
     }
 
     pub fn add_h2h(&self, h2h: &Head2Head) -> Result<()> {
-        let conn = &self.0;
-        conn.execute(
-            "INSERT OR IGNORE INTO H2H (awayID, homeID, totalGames, teamWins, teamWinFreq) values (?1, ?2, ?3, ?4, ?5);",
-            params![h2h.team1,h2h.team2,h2h.total_games,h2h.team_wins,h2h.team_win_freq],
-        )?;
+        let conn = self.conn();
+        conn.prepare_cached(
+            "
+        INSERT OR IGNORE INTO H2H
+            (awayID, homeID, totalGames, teamWins, teamWinFreq, otLosses, score)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);
+        ",
+        )?
+        .execute(params![
+            h2h.team1,
+            h2h.team2,
+            h2h.total_games,
+            h2h.team_wins,
+            h2h.team_win_freq,
+            h2h.ot_losses,
+            h2h.score
+        ])?;
         Ok(())
     }
 
     pub fn get_h2h(&self, team1: impl Into<TeamID>, team2: impl Into<TeamID>) -> Result<Head2Head> {
         let id1 = team1.into();
         let id2 = team2.into();
-        let conn = &self.0;
-        let h2h = conn.query_row(
-            "SELECT * FROM H2H WHERE awayID = ?1 AND homeID = ?2;",
-            params![id1, id2],
-            |row| Head2Head::try_from(row),
-        )?;
+        if let Some(memory) = self.memory().as_ref() {
+            return memory
+                .h2h
+                .get(&(id1, id2))
+                .cloned()
+                .ok_or(QueryReturnedNoRows);
+        }
+        let conn = self.conn();
+        let h2h = conn
+            .prepare_cached("SELECT * FROM H2H WHERE awayID = ?1 AND homeID = ?2;")?
+            .query_row(params![id1, id2], |row| Head2Head::try_from(row))?;
         Ok(h2h)
     }
 
     pub fn add_last10(&self, id: impl Into<TeamID>) -> Result<()> {
         let id = id.into();
-        let conn = &self.0;
-        conn.execute(
-            "INSERT OR IGNORE INTO last10 (id) VALUES (?1);",
-            params![id],
-        )?;
+        let conn = self.conn();
+        conn.prepare_cached("INSERT OR IGNORE INTO last10 (id) VALUES (?1);")?
+            .execute(params![id])?;
         Ok(())
     }
 
     pub fn get_last10(&self, id: impl Into<TeamID>) -> Result<Last10> {
         let id = id.into();
-        let conn = &self.0;
-        let last10 = conn.query_row("SELECT * FROM last10 WHERE id = ?1;", params![id], |row| {
-            Last10::try_from(row)
-        })?;
+        if let Some(memory) = self.memory().as_ref() {
+            return memory.last10.get(&id).cloned().ok_or(QueryReturnedNoRows);
+        }
+        let conn = self.conn();
+        let last10 = conn
+            .prepare_cached("SELECT * FROM last10 WHERE id = ?1;")?
+            .query_row(params![id], |row| Last10::try_from(row))?;
         Ok(last10)
     }
 
-    pub fn get_team_abbrev(&self, abbrev: &str) -> Result<Team> {
-        let conn = &self.0;
-        let team = conn.query_row(
-            "SELECT * FROM teams WHERE abbreviation = ?1;",
-            params![abbrev],
-            |row| Team::try_from(row),
+    /// Records an era of the franchise registry, replacing the one with the
+    /// same team id and tricode
+    pub fn add_team_era(&self, era: &TeamEra) -> Result<()> {
+        let conn = self.conn();
+        conn.prepare_cached(
+            "
+        INSERT INTO team_history (teamID, franchiseID, name, abbreviation, validFrom, validTo)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        ON CONFLICT (teamID, abbreviation) DO UPDATE SET
+            franchiseID = COALESCE(excluded.franchiseID, franchiseID),
+            name = excluded.name,
+            validFrom = excluded.validFrom,
+            validTo = excluded.validTo;
+        ",
+        )?
+        .execute(params![
+            era.team_id,
+            era.franchise_id,
+            era.name,
+            era.abbrev,
+            era.valid_from,
+            era.valid_to
+        ])?;
+        Ok(())
+    }
+
+    pub fn get_team_eras(&self, id: impl Into<TeamID>) -> Result<Vec<TeamEra>> {
+        let id = id.into();
+        let conn = self.conn();
+        let mut stmnt = conn
+            .prepare_cached("SELECT * FROM team_history WHERE teamID = ?1 ORDER BY validFrom;")?;
+        let eras = stmnt
+            .query_map(params![id], |row| TeamEra::try_from(row))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(eras)
+    }
+
+    /// Team that went by `abbrev` on `date`. Tricodes missing from the
+    /// registry fall back to the teams' current ones.
+    pub fn get_team_abbrev(&self, abbrev: &str, date: NaiveDate) -> Result<Team> {
+        let id: TeamID = self
+            .conn()
+            .prepare_cached(
+                "
+            SELECT COALESCE(
+                (SELECT teamID FROM team_history
+                 WHERE abbreviation = ?1
+                   AND (validFrom IS NULL OR validFrom <= ?2)
+                   AND (validTo IS NULL OR validTo > ?2)
+                 ORDER BY validFrom DESC
+                 LIMIT 1),
+                (SELECT id FROM teams WHERE abbreviation = ?1 ORDER BY id DESC LIMIT 1)
+            );
+            ",
+            )?
+            .query_row(params![abbrev, date], |row| row.get(0))
+            .map_err(|err| match err {
+                rusqlite::Error::InvalidColumnType(..) => QueryReturnedNoRows,
+                err => err,
+            })?;
+        self.get_team(id)
+    }
+
+    /// Teams that took over from another team of the same franchise, as
+    /// `(predecessor, successor, date)`
+    pub fn successions(&self) -> Result<Vec<(TeamID, TeamID, NaiveDate)>> {
+        let conn = self.conn();
+        let mut stmnt = conn.prepare_cached(
+            "
+        SELECT DISTINCT old.teamID, new.teamID, new.validFrom
+        FROM team_history AS new
+        JOIN team_history AS old
+            ON old.franchiseID = new.franchiseID
+            AND old.validTo = new.validFrom
+            AND old.teamID != new.teamID
+        ORDER BY new.validFrom;
+        ",
         )?;
-        Ok(team)
+        let successions = stmnt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(successions)
+    }
+
+    fn team_ids(&self) -> Result<Vec<TeamID>> {
+        if let Some(memory) = self.memory().as_ref() {
+            return Ok(memory.teams.keys().copied().collect());
+        }
+        let conn = self.conn();
+        let mut stmnt = conn.prepare_cached("SELECT id FROM teams;")?;
+        let ids = stmnt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ids)
+    }
+
+    /// Hands `from`'s ratings and head to head records over to `to`
+    pub fn carry_over(&self, from: impl Into<TeamID>, to: impl Into<TeamID>) -> Result<()> {
+        let (from, to) = (from.into(), to.into());
+        for system in RatingSystem::ALL {
+            self.update_rating(to, self.get_rating(from, system)?)?;
+        }
+        for other in self.team_ids()? {
+            if other == from || other == to {
+                continue;
+            }
+            if let Some(h2h) = self.get_h2h(from, other).optional()? {
+                self.update_h2h(Head2Head {
+                    team1: to as u32,
+                    ..h2h
+                })?;
+            }
+            if let Some(h2h) = self.get_h2h(other, from).optional()? {
+                self.update_h2h(Head2Head {
+                    team2: to as u32,
+                    ..h2h
+                })?;
+            }
+        }
+        Ok(())
     }
 
     pub fn update_team_rating(
@@ -123,44 +456,477 @@ impl DataBase {
         new_rating: WengLinRating,
     ) -> Result<()> {
         let id = id.into();
-        let conn = &self.0;
-        conn.execute(
-            "UPDATE teams SET rating = ?1, uncertainty = ?2 WHERE id = ?3;",
-            params![new_rating.rating, new_rating.uncertainty, id],
-        )?;
+        if let Some(memory) = self.memory().as_mut() {
+            if let Some(team) = memory.teams.get_mut(&id) {
+                team.update(new_rating);
+            }
+            return Ok(());
+        }
+        self.write_team_rating(id, new_rating)
+    }
+
+    fn write_team_rating(&self, id: TeamID, new_rating: WengLinRating) -> Result<()> {
+        let conn = self.conn();
+        conn.prepare_cached("UPDATE teams SET rating = ?1, uncertainty = ?2 WHERE id = ?3;")?
+            .execute(params![new_rating.rating, new_rating.uncertainty, id])?;
+        Ok(())
+    }
+
+    /// A team's rating in `system`. Weng-Lin ratings live in `teams`, the
+    /// others start out at the system's default the first time they're read.
+    pub fn get_rating(&self, id: impl Into<TeamID>, system: RatingSystem) -> Result<Rating> {
+        let id = id.into();
+        if system == RatingSystem::WengLin {
+            return self.get_team(id).map(|team| Rating::WengLin(team.rating));
+        }
+        if let Some(memory) = self.memory().as_ref() {
+            return Ok(memory
+                .ratings
+                .get(&(id, system))
+                .copied()
+                .unwrap_or_else(|| system.new_rating()));
+        }
+        let conn = self.conn();
+        let rating = conn
+            .prepare_cached("SELECT rating FROM ratings WHERE teamID = ?1 AND system = ?2;")?
+            .query_row(params![id, system.name()], |row| rating_from_json(row, 0))
+            .optional()?;
+        Ok(rating.unwrap_or_else(|| system.new_rating()))
+    }
+
+    pub fn update_rating(&self, id: impl Into<TeamID>, rating: Rating) -> Result<()> {
+        let id = id.into();
+        if let Rating::WengLin(rating) = rating {
+            return self.update_team_rating(id, rating);
+        }
+        if let Some(memory) = self.memory().as_mut() {
+            memory.ratings.insert((id, rating.system()), rating);
+            return Ok(());
+        }
+        self.write_rating(id, &rating)
+    }
+
+    fn write_rating(&self, id: TeamID, rating: &Rating) -> Result<()> {
+        let json = serde_json::to_string(rating)
+            .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?;
+        let conn = self.conn();
+        conn.prepare_cached(
+            "
+        INSERT INTO ratings (teamID, system, rating) VALUES (?1, ?2, ?3)
+        ON CONFLICT (teamID, system) DO UPDATE SET rating = excluded.rating;
+        ",
+        )?
+        .execute(params![id, rating.system().name(), json])?;
         Ok(())
     }
 
     pub fn update_h2h(&self, h2h: Head2Head) -> Result<()> {
-        let conn = &self.0;
-        conn.execute(
-            "UPDATE H2H SET totalGames = ?1, teamWins = ?2, teamWinFreq = ?3 WHERE awayID = ?4 AND homeID = ?5;",
-            params![h2h.total_games,h2h.team_wins,h2h.team_win_freq,h2h.team1,h2h.team2]
-        )?;
+        if let Some(memory) = self.memory().as_mut() {
+            let key = (h2h.team1.into(), h2h.team2.into());
+            if let Some(stored) = memory.h2h.get_mut(&key) {
+                *stored = h2h;
+            }
+            return Ok(());
+        }
+        self.write_h2h(&h2h)
+    }
+
+    fn write_h2h(&self, h2h: &Head2Head) -> Result<()> {
+        let conn = self.conn();
+        conn.prepare_cached(
+            "
+        UPDATE H2H
+        SET totalGames = ?1, teamWins = ?2, teamWinFreq = ?3, otLosses = ?4, score = ?5
+        WHERE awayID = ?6 AND homeID = ?7;
+        ",
+        )?
+        .execute(params![
+            h2h.total_games,
+            h2h.team_wins,
+            h2h.team_win_freq,
+            h2h.ot_losses,
+            h2h.score,
+            h2h.team1,
+            h2h.team2
+        ])?;
         Ok(())
     }
 
     pub fn update_last10(&self, last10: Last10) -> Result<()> {
-        let conn = &self.0;
-        let games = last10.games;
-        let mut games_num = 0;
-        for (idx, game) in games.iter().rev().enumerate() {
-            let i = if *game == Outcomes::WIN { 1 } else { 0 };
-            games_num |= i << idx;
-        }
-        conn.execute(
+        if let Some(memory) = self.memory().as_mut() {
+            if let Some(stored) = memory.last10.get_mut(&last10.id.into()) {
+                *stored = last10;
+            }
+            return Ok(());
+        }
+        self.write_last10(&last10)
+    }
+
+    fn write_last10(&self, last10: &Last10) -> Result<()> {
+        let conn = self.conn();
+        let games = &last10.games;
+        let (mut games_num, mut overtime, mut ties) = (0, 0, 0);
+        for (idx, &(game, ot)) in games.iter().rev().enumerate() {
+            games_num |= ((game == Outcomes::WIN) as u32) << idx;
+            ties |= ((game == Outcomes::DRAW) as u32) << idx;
+            overtime |= (ot as u32) << idx;
+        }
+        conn.prepare_cached(
             "
         UPDATE last10
-        SET wins = ?2, losses = ?3, games = ?4
+        SET wins = ?2, losses = ?3, games = ?4, otLosses = ?5, overtime = ?6, ties = ?7,
+            played = ?8
         WHERE id = ?1;
         ",
-            params![last10.id, last10.wins, last10.loss, games_num],
+        )?
+        .execute(params![
+            last10.id,
+            last10.wins,
+            last10.loss,
+            games_num,
+            last10.otl,
+            overtime,
+            ties,
+            last10.played
+        ])?;
+        Ok(())
+    }
+
+    /// Goals in a team's latest games, none before its first
+    pub fn get_goal_form(&self, id: impl Into<TeamID>) -> Result<GoalForm> {
+        let id = id.into();
+        if let Some(memory) = self.memory().as_ref() {
+            return Ok(memory
+                .goal_form
+                .get(&id)
+                .cloned()
+                .unwrap_or_else(|| GoalForm::new(id)));
+        }
+        let conn = self.conn();
+        let form = conn
+            .prepare_cached("SELECT teamID, games FROM goal_form WHERE teamID = ?1;")?
+            .query_row(params![id], goal_form_from_row)
+            .optional()?;
+        Ok(form.unwrap_or_else(|| GoalForm::new(id)))
+    }
+
+    pub fn update_goal_form(&self, form: GoalForm) -> Result<()> {
+        if let Some(memory) = self.memory().as_mut() {
+            memory.goal_form.insert(form.id, form);
+            return Ok(());
+        }
+        self.write_goal_form(&form)
+    }
+
+    fn write_goal_form(&self, form: &GoalForm) -> Result<()> {
+        let json = serde_json::to_string(&form.games)
+            .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?;
+        let conn = self.conn();
+        conn.prepare_cached(
+            "
+        INSERT INTO goal_form (teamID, games) VALUES (?1, ?2)
+        ON CONFLICT (teamID) DO UPDATE SET games = excluded.games;
+        ",
+        )?
+        .execute(params![form.id, json])?;
+        Ok(())
+    }
+
+    /// A team's attack and defence strengths, league average before its
+    /// first game
+    pub fn get_strength(&self, id: impl Into<TeamID>) -> Result<Strength> {
+        let id = id.into();
+        if let Some(memory) = self.memory().as_ref() {
+            return Ok(memory
+                .strengths
+                .get(&id)
+                .copied()
+                .unwrap_or_else(|| Strength::new(id)));
+        }
+        let conn = self.conn();
+        let strength = conn
+            .prepare_cached("SELECT teamID, attack, defence FROM strengths WHERE teamID = ?1;")?
+            .query_row(params![id], strength_from_row)
+            .optional()?;
+        Ok(strength.unwrap_or_else(|| Strength::new(id)))
+    }
+
+    pub fn update_strength(&self, strength: Strength) -> Result<()> {
+        if let Some(memory) = self.memory().as_mut() {
+            memory.strengths.insert(strength.id, strength);
+            return Ok(());
+        }
+        self.write_strength(&strength)
+    }
+
+    fn write_strength(&self, strength: &Strength) -> Result<()> {
+        let conn = self.conn();
+        conn.prepare_cached(
+            "
+        INSERT INTO strengths (teamID, attack, defence) VALUES (?1, ?2, ?3)
+        ON CONFLICT (teamID) DO UPDATE SET attack = excluded.attack, defence = excluded.defence;
+        ",
+        )?
+        .execute(params![strength.id, strength.attack, strength.defence])?;
+        Ok(())
+    }
+
+    /// Last day the backfill went through completely
+    pub fn get_checkpoint(&self) -> Result<Option<NaiveDate>> {
+        if let Some(date) = self.memory().as_ref().and_then(|memory| memory.checkpoint) {
+            return Ok(Some(date));
+        }
+        let conn = self.conn();
+        conn.prepare_cached("SELECT lastDate FROM checkpoint WHERE id = 0;")?
+            .query_row([], |row| row.get(0))
+            .optional()
+    }
+
+    pub fn set_checkpoint(&self, date: NaiveDate) -> Result<()> {
+        if let Some(memory) = self.memory().as_mut() {
+            memory.checkpoint = Some(date);
+            return Ok(());
+        }
+        self.write_checkpoint(date)
+    }
+
+    fn write_checkpoint(&self, date: NaiveDate) -> Result<()> {
+        let conn = self.conn();
+        conn.prepare_cached(
+            "INSERT INTO checkpoint (id, lastDate) VALUES (0, ?1)
+            ON CONFLICT (id) DO UPDATE SET lastDate = excluded.lastDate;",
+        )?
+        .execute(params![date])?;
+        Ok(())
+    }
+
+    pub fn is_processed(&self, game_id: i64) -> Result<bool> {
+        if let Some(memory) = self.memory().as_ref()
+            && memory.processed.contains(&game_id)
+        {
+            return Ok(true);
+        }
+        let conn = self.conn();
+        conn.prepare_cached("SELECT EXISTS (SELECT 1 FROM processed WHERE gameID = ?1);")?
+            .query_row(params![game_id], |row| row.get(0))
+    }
+
+    pub fn mark_processed(&self, game_id: i64) -> Result<()> {
+        if let Some(memory) = self.memory().as_mut() {
+            memory.processed.insert(game_id);
+            return Ok(());
+        }
+        self.write_processed(game_id)
+    }
+
+    fn write_processed(&self, game_id: i64) -> Result<()> {
+        let conn = self.conn();
+        conn.prepare_cached("INSERT OR IGNORE INTO processed (gameID) VALUES (?1);")?
+            .execute(params![game_id])?;
+        Ok(())
+    }
+
+    /// Stores a game unless one with the same id is already there, returning
+    /// whether it was inserted
+    pub fn add_game(&self, game: &Game) -> Result<bool> {
+        let conn = self.conn();
+        let inserted = conn
+            .prepare_cached(
+                "
+        INSERT OR IGNORE INTO games
+            (id, date, season, gameType, awayID, homeID, awayScore, homeScore, resolution)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);
+        ",
+            )?
+            .execute(params![
+                game.id,
+                game.date,
+                game.season,
+                game.game_type.to_int(),
+                game.away_id,
+                game.home_id,
+                game.score.0,
+                game.score.1,
+                game.resolution.code()
+            ])?;
+        Ok(inserted > 0)
+    }
+
+    pub fn get_game(&self, id: i64) -> Result<Game> {
+        let conn = self.conn();
+        conn.prepare_cached("SELECT * FROM games WHERE id = ?1;")?
+            .query_row(params![id], |row| Game::try_from(row))
+    }
+
+    /// Every stored game in the order it was played
+    pub fn get_games(&self) -> Result<Vec<Game>> {
+        let conn = self.conn();
+        let mut stmnt = conn.prepare("SELECT * FROM games ORDER BY date, id;")?;
+        let games = stmnt
+            .query_map([], |row| Game::try_from(row))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(games)
+    }
+
+    /// Stored games `away` played at `home` before `as_of`, a date and game id,
+    /// from season `since` on. The most recent come first, `limit` of them at
+    /// most.
+    pub fn meetings(
+        &self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+        as_of: (NaiveDate, i64),
+        since: u32,
+        limit: Option<usize>,
+    ) -> Result<Vec<Game>> {
+        let (date, id) = as_of;
+        let limit = limit.map_or(-1, |limit| limit as i64);
+        let conn = self.conn();
+        let mut stmnt = conn.prepare_cached(
+            "
+        SELECT * FROM games
+        WHERE awayID = ?1 AND homeID = ?2 AND (date < ?3 OR (date = ?3 AND id < ?4)) AND season >= ?5
+        ORDER BY date DESC, id DESC
+        LIMIT ?6;
+        ",
+        )?;
+        let games = stmnt
+            .query_map(
+                params![away.into(), home.into(), date, id, since, limit],
+                |row| Game::try_from(row),
+            )?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(games)
+    }
+
+    /// Latest season with a processed game
+    pub fn last_processed_season(&self) -> Result<Option<u32>> {
+        let conn = self.conn();
+        conn.prepare_cached(
+            "SELECT MAX(season) FROM games JOIN processed ON processed.gameID = games.id;",
+        )?
+        .query_row([], |row| row.get(0))
+    }
+
+    /// Every team with a stored game in `season`
+    pub fn season_teams(&self, season: u32) -> Result<Vec<TeamID>> {
+        let conn = self.conn();
+        let mut stmnt = conn.prepare_cached(
+            "SELECT awayID FROM games WHERE season = ?1 UNION SELECT homeID FROM games WHERE season = ?1;",
         )?;
+        let teams = stmnt
+            .query_map(params![season], |row| row.get(0))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(teams)
+    }
+
+    /// Number of stored games and the home teams' average score in them,
+    /// ties counting as half a win
+    pub fn home_record(&self) -> Result<(usize, f64)> {
+        let conn = self.conn();
+        conn.prepare_cached(
+            "
+        SELECT COUNT(*), COALESCE(AVG(
+            CASE
+                WHEN homeScore > awayScore THEN 1.0
+                WHEN homeScore = awayScore THEN 0.5
+                ELSE 0.0
+            END
+        ), 0.5)
+        FROM games;
+        ",
+        )?
+        .query_row([], |row| Ok((row.get(0)?, row.get(1)?)))
+    }
+
+    /// Number of stored games and the goals the away and home teams scored in
+    /// them on average by the end of regulation, the deciding goal of
+    /// overtimes and shootouts left out
+    pub fn goal_rates(&self) -> Result<(usize, f64, f64)> {
+        let conn = self.conn();
+        conn.prepare_cached(
+            "
+        SELECT COUNT(*),
+            COALESCE(AVG(CASE WHEN resolution = 'REG' THEN awayScore ELSE MIN(awayScore, homeScore) END), 0.0),
+            COALESCE(AVG(CASE WHEN resolution = 'REG' THEN homeScore ELSE MIN(awayScore, homeScore) END), 0.0)
+        FROM games;
+        ",
+        )?
+        .query_row([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+    }
+
+    /// Average uncertainty of the teams' ratings in `system`, `None` for Elo
+    /// or without teams
+    pub fn mean_uncertainty(&self, system: RatingSystem) -> Result<Option<f64>> {
+        let ids = self.team_ids()?;
+        let mut total = 0.;
+        for &id in &ids {
+            match self.get_rating(id, system)?.uncertainty() {
+                Some(uncertainty) => total += uncertainty,
+                None => return Ok(None),
+            }
+        }
+        Ok((!ids.is_empty()).then(|| total / ids.len() as f64))
+    }
+
+    /// Every team with its rating in `system`, best first
+    pub fn get_ratings(&self, system: RatingSystem) -> Result<Vec<(Team, Rating)>> {
+        let mut teams = self
+            .team_ids()?
+            .into_iter()
+            .map(|id| Ok((self.get_team(id)?, self.get_rating(id, system)?)))
+            .collect::<Result<Vec<_>>>()?;
+        teams.sort_by(|(_, r1), (_, r2)| r2.mean().total_cmp(&r1.mean()));
+        Ok(teams)
+    }
+
+    /// Stores the calibrator fitted to `model` as set up by `settings`, see
+    /// [`GameModel::settings`]
+    ///
+    /// [`GameModel::settings`]: crate::model::model::GameModel::settings
+    pub fn set_calibrator(
+        &self,
+        model: &str,
+        calibrator: &Calibrator,
+        games: usize,
+        settings: &str,
+    ) -> Result<()> {
+        let json = serde_json::to_string(calibrator)
+            .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?;
+        let conn = self.conn();
+        conn.prepare_cached(
+            "
+        INSERT INTO calibrators (model, calibrator, games, settings) VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT (model) DO UPDATE SET
+            calibrator = excluded.calibrator,
+            games = excluded.games,
+            settings = excluded.settings;
+        ",
+        )?
+        .execute(params![model, json, games, settings])?;
         Ok(())
     }
 
+    /// The calibrator stored for `model`, with the settings it was fitted to
+    pub fn get_calibrator(&self, model: &str) -> Result<Option<(Calibrator, String)>> {
+        let conn = self.conn();
+        conn.prepare_cached("SELECT calibrator, settings FROM calibrators WHERE model = ?1;")?
+            .query_row(params![model], |row| {
+                let json: String = row.get(0)?;
+                let calibrator = serde_json::from_str(&json).map_err(|err| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        0,
+                        rusqlite::types::Type::Text,
+                        Box::new(err),
+                    )
+                })?;
+                Ok((calibrator, row.get(1)?))
+            })
+            .optional()
+    }
+
     pub fn get_top(&self, n: u64) -> Result<Vec<Team>> {
-        let conn = &self.0;
+        let conn = self.conn();
         let mut teams = Vec::with_capacity(32);
         let mut stmnt = conn.prepare("SELECT * FROM teams ORDER BY rating DESC LIMIT ?1")?;
         let mut rows = stmnt.query(params![n])?;
@@ -171,8 +937,14 @@ impl DataBase {
         Ok(teams)
     }
 
+    pub fn count_teams(&self) -> Result<usize> {
+        let conn = self.conn();
+        conn.prepare_cached("SELECT COUNT(*) FROM teams;")?
+            .query_row([], |row| row.get(0))
+    }
+
     pub fn get_bot(&self, n: u64) -> Result<Vec<Team>> {
-        let conn = &self.0;
+        let conn = self.conn();
         let mut teams = Vec::with_capacity(32);
         let mut stmnt = conn.prepare("SELECT * FROM teams ORDER BY rating ASC LIMIT ?1")?;
         let mut rows = stmnt.query(params![n])?;
@@ -184,6 +956,32 @@ impl DataBase {
     }
 }
 
+fn goal_form_from_row(row: &rusqlite::Row<'_>) -> Result<GoalForm> {
+    let json: String = row.get(1)?;
+    let games = serde_json::from_str(&json).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(err))
+    })?;
+    Ok(GoalForm {
+        id: row.get(0)?,
+        games,
+    })
+}
+
+fn strength_from_row(row: &rusqlite::Row<'_>) -> Result<Strength> {
+    Ok(Strength {
+        id: row.get(0)?,
+        attack: row.get(1)?,
+        defence: row.get(2)?,
+    })
+}
+
+fn rating_from_json(row: &rusqlite::Row<'_>, idx: usize) -> Result<Rating> {
+    let json: String = row.get(idx)?;
+    serde_json::from_str(&json).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(err))
+    })
+}
+
 // pub fn get_or_create_team(conn: &Connection, team: &Team) -> Result<i64> {
 //     conn.execute(
 //         "INSERT OR IGNORE INTO teams (id, name, abbreviation) VALUES (?1, ?2, ?3)",
@@ -217,16 +1015,120 @@ mod tests {
     use chrono::NaiveDate;
     use env_logger::Env;
     use log::info;
-    use nhl_api::{Client, GameDate};
+    use nhl_api::{Client, GameDate, GameType, PeriodType};
     use skillratings::{
         Outcomes,
         weng_lin::{WengLinConfig, weng_lin},
     };
 
-    use crate::rating::openskill::SkillRating;
+    use crate::{data::models::franchises::known_eras, rating::openskill::SkillRating};
 
     use super::*;
-    const PATH: &'static str = "src/data/nhl.db";
+
+    #[test]
+    fn checkpoint_and_processed_games() -> anyhow::Result<()> {
+        let db = DataBase::new(":memory:")?;
+        assert_eq!(db.get_checkpoint()?, None);
+        let date = NaiveDate::from_ymd_opt(1990, 1, 2).unwrap();
+        db.set_checkpoint(date)?;
+        db.set_checkpoint(date.succ_opt().unwrap())?;
+        assert_eq!(db.get_checkpoint()?, date.succ_opt());
+
+        assert!(!db.is_processed(1989020001)?);
+        db.mark_processed(1989020001)?;
+        db.mark_processed(1989020001)?;
+        assert!(db.is_processed(1989020001)?);
+        Ok(())
+    }
+
+    #[test]
+    fn failed_transactions_roll_back() -> anyhow::Result<()> {
+        let db = DataBase::new(":memory:")?;
+        let result = db.transaction(|| {
+            db.mark_processed(1)?;
+            db.transaction(|| db.mark_processed(2))?;
+            db.get_team(8).map(|_| ())
+        });
+        assert!(result.is_err());
+        assert!(!db.is_processed(1)?);
+        assert!(!db.is_processed(2)?);
+        Ok(())
+    }
+
+    #[test]
+    fn franchise_registry() -> anyhow::Result<()> {
+        let db = DataBase::new(":memory:")?;
+        for (id, name) in [
+            (53, "Arizona Coyotes"),
+            (59, "Utah Hockey Club"),
+            (68, "Utah Mammoth"),
+        ] {
+            db.add_team(id, name.to_string(), "UTA".to_string())?;
+        }
+        let franchise = |id| match id {
+            53 => Some(28),
+            59 | 68 => Some(40),
+            _ => None,
+        };
+        for era in known_eras(franchise) {
+            db.add_team_era(&era)?;
+        }
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(db.get_team_abbrev("UTA", day(2025, 1, 10))?.id, 59);
+        assert_eq!(db.get_team_abbrev("UTA", day(2025, 10, 10))?.id, 68);
+        assert_eq!(db.get_team_abbrev("ARI", day(2020, 1, 10))?.id, 53);
+        assert!(db.get_team_abbrev("QUE", day(2020, 1, 10)).is_err());
+        // Arizona and Utah are separate franchises
+        assert_eq!(db.successions()?, vec![(59, 68, day(2025, 7, 1))]);
+
+        let rating = WengLinRating {
+            rating: 27.5,
+            uncertainty: 5.0,
+        };
+        db.update_team_rating(59, rating)?;
+        db.carry_over(59, 68)?;
+        assert_eq!(db.get_team(68)?.rating, rating);
+        Ok(())
+    }
+
+    #[test]
+    fn reset_starts_ratings_over() -> anyhow::Result<()> {
+        let db = DataBase::new(":memory:")?;
+        db.add_team(8, "Montréal Canadiens".to_string(), "MTL".to_string())?;
+        for system in RatingSystem::ALL {
+            assert_eq!(db.get_rating(8, system)?, system.new_rating());
+            let rating = system.new_rating();
+            db.update_rating(8, rating.with_mean(rating.mean() + 1.))?;
+        }
+        db.reset_state()?;
+        for system in RatingSystem::ALL {
+            assert_eq!(db.get_rating(8, system)?, system.new_rating());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn games_round_trip() -> anyhow::Result<()> {
+        let db = DataBase::new(":memory:")?;
+        let game = Game {
+            id: 2023020204,
+            date: NaiveDate::from_ymd_opt(2023, 11, 11).unwrap(),
+            season: 20232024,
+            game_type: GameType::RegularSeason,
+            away_id: 8,
+            home_id: 10,
+            score: (3, 2),
+            resolution: PeriodType::Shootout,
+        };
+        assert!(db.add_game(&game)?);
+        assert!(!db.add_game(&game)?);
+        let stored = db.get_game(game.id)?;
+        assert_eq!(stored.date, game.date);
+        assert_eq!(stored.score, game.score);
+        assert!(stored.is_overtime());
+        assert_eq!(db.get_games()?.len(), 1);
+        Ok(())
+    }
 
     #[tokio::test]
 
@@ -242,7 +1144,7 @@ mod tests {
         info!("Starting NHL Ranker...");
 
         info!("Fetching database");
-        let db = DataBase::new(PATH)?;
+        let db = DataBase::new(":memory:")?;
         info!("Database started succesfully");
 
         info!("Adding Teams");
@@ -286,8 +1188,8 @@ mod tests {
             home.abbrev,
             home.score,
         );
-        let away_team = db.get_team_abbrev(&away.abbrev)?;
-        let home_team = db.get_team_abbrev(&home.abbrev)?;
+        let away_team = db.get_team_abbrev(&away.abbrev, date)?;
+        let home_team = db.get_team_abbrev(&home.abbrev, date)?;
         info!(
             "The {} are rated: {}",
             away_team.name,
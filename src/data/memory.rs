@@ -0,0 +1,32 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDate;
+
+use crate::{
+    data::{
+        db::TeamID,
+        models::{
+            goals::GoalForm, head2head::Head2Head, last10::Last10, strength::Strength, teams::Team,
+        },
+    },
+    rating::system::{Rating, RatingSystem},
+};
+
+/// Everything the models read and write for each game, held in memory while
+/// the database runs in memory mode. Changes only reach SQLite when a
+/// snapshot is taken, along with the games and checkpoint they account for.
+#[derive(Debug, Default)]
+pub struct MemoryState {
+    pub teams: HashMap<TeamID, Team>,
+    pub h2h: HashMap<(TeamID, TeamID), Head2Head>,
+    pub last10: HashMap<TeamID, Last10>,
+    /// Ratings kept outside of `teams`, see [`DataBase::get_rating`]
+    ///
+    /// [`DataBase::get_rating`]: crate::data::db::DataBase::get_rating
+    pub ratings: HashMap<(TeamID, RatingSystem), Rating>,
+    pub goal_form: HashMap<TeamID, GoalForm>,
+    pub strengths: HashMap<TeamID, Strength>,
+    /// Games processed since the last snapshot
+    pub processed: HashSet<i64>,
+    pub checkpoint: Option<NaiveDate>,
+}
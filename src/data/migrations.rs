@@ -0,0 +1,189 @@
+use anyhow::{Context, bail};
+use log::info;
+use rusqlite::Connection;
+
+/// Schema upgrades in order. The database's `user_version` is the number of
+/// steps already applied, so a step must never be edited once released; add a
+/// new one instead.
+///
+/// The first steps use `IF NOT EXISTS` so that files created before the
+/// schema was versioned are adopted as they are.
+const MIGRATIONS: &[&str] = &[
+    // 1: ratings, head to heads and last 10 games
+    "
+    CREATE TABLE IF NOT EXISTS teams (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        abbreviation TEXT NOT NULL,
+        rating REAL DEFAULT 25.0,
+        uncertainty REAL DEFAULT 8.33
+    );
+    CREATE TABLE IF NOT EXISTS H2H (
+        awayID INTEGER,
+        homeID INTEGER,
+        totalGames INTEGER DEFAULT 0,
+        teamWins INTEGER DEFAULT 0,
+        teamWinFreq REAL DEFAULT 0.0,
+        PRIMARY KEY (awayID, homeID)
+    );
+    CREATE TABLE IF NOT EXISTS last10 (
+        id INTEGER PRIMARY KEY,
+        wins INTEGER DEFAULT 0,
+        losses INTEGER DEFAULT 0,
+        games INTEGER DEFAULT 0
+    );
+    ",
+    // 2: backfill checkpoint
+    "
+    CREATE TABLE IF NOT EXISTS checkpoint (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        lastDate TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS processed (
+        gameID INTEGER PRIMARY KEY
+    );
+    ",
+    // 3: stored games
+    "
+    CREATE TABLE IF NOT EXISTS games (
+        id INTEGER PRIMARY KEY,
+        date TEXT NOT NULL,
+        season INTEGER NOT NULL,
+        gameType INTEGER NOT NULL,
+        awayID INTEGER NOT NULL,
+        homeID INTEGER NOT NULL,
+        awayScore INTEGER NOT NULL,
+        homeScore INTEGER NOT NULL,
+        resolution TEXT NOT NULL DEFAULT 'REG'
+    );
+    CREATE INDEX IF NOT EXISTS games_by_date ON games (date, id);
+    ",
+    // 4: franchise registry
+    "
+    CREATE TABLE team_history (
+        teamID INTEGER NOT NULL,
+        franchiseID INTEGER,
+        name TEXT NOT NULL,
+        abbreviation TEXT NOT NULL,
+        validFrom TEXT,
+        validTo TEXT,
+        PRIMARY KEY (teamID, abbreviation)
+    );
+    CREATE INDEX team_history_by_abbrev ON team_history (abbreviation);
+    ",
+    // 5: overtime results, ties count with overtime losses
+    "
+    ALTER TABLE H2H ADD COLUMN otLosses INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE H2H ADD COLUMN score REAL NOT NULL DEFAULT 0.0;
+    UPDATE H2H SET score = teamWins;
+    ALTER TABLE last10 ADD COLUMN otLosses INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE last10 ADD COLUMN overtime INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE last10 ADD COLUMN ties INTEGER NOT NULL DEFAULT 0;
+    ",
+    // 6: ratings of the systems other than Weng-Lin, which stays in teams
+    "
+    CREATE TABLE ratings (
+        teamID INTEGER NOT NULL,
+        system TEXT NOT NULL,
+        rating TEXT NOT NULL,
+        PRIMARY KEY (teamID, system)
+    );
+    ",
+    // 7: fitted probability calibrators, by model
+    "
+    CREATE TABLE calibrators (
+        model TEXT PRIMARY KEY,
+        calibrator TEXT NOT NULL,
+        games INTEGER NOT NULL
+    );
+    ",
+    // 8: last games kept past 10, with the number played so that unplayed
+    // games aren't read as losses. Older rows can't tell, so a full record
+    // is assumed until the next replay.
+    "
+    ALTER TABLE last10 ADD COLUMN played INTEGER NOT NULL DEFAULT 0;
+    UPDATE last10 SET played = 10 WHERE wins + losses + otLosses > 0;
+    ",
+    // 9: goals for and against in each team's latest games, as JSON
+    "
+    CREATE TABLE goal_form (
+        teamID INTEGER PRIMARY KEY,
+        games TEXT NOT NULL
+    );
+    ",
+    // 10: attack and defence strengths of the Poisson model
+    "
+    CREATE TABLE strengths (
+        teamID INTEGER PRIMARY KEY,
+        attack REAL NOT NULL,
+        defence REAL NOT NULL
+    );
+    ",
+    // 11: head to head meetings read back from the stored games
+    "
+    CREATE INDEX games_by_matchup ON games (awayID, homeID, date, id);
+    ",
+    // 12: how each calibrated model was set up, calibrators stored before
+    // then matching none
+    "
+    ALTER TABLE calibrators ADD COLUMN settings TEXT NOT NULL DEFAULT '';
+    ",
+];
+
+/// Version a fully migrated database is at
+pub const SCHEMA_VERSION: usize = MIGRATIONS.len();
+
+pub fn version(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.query_row("PRAGMA user_version;", [], |row| row.get(0))
+}
+
+/// Applies every step the database is missing, each in its own transaction
+pub fn migrate(conn: &mut Connection) -> anyhow::Result<()> {
+    let current = version(conn)?;
+    if current > SCHEMA_VERSION {
+        bail!(
+            "database schema is at version {current} but this build only knows up to version \
+             {SCHEMA_VERSION}, update the ranker before opening it"
+        );
+    }
+    for (i, step) in MIGRATIONS.iter().enumerate().skip(current) {
+        let target = i + 1;
+        info!("Migrating database to version {target}");
+        let tx = conn.transaction()?;
+        tx.execute_batch(step)
+            .and_then(|_| tx.pragma_update(None, "user_version", target))
+            .with_context(|| format!("failed to migrate database to version {target}"))?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrades_unversioned_files() -> anyhow::Result<()> {
+        let mut conn = Connection::open_in_memory()?;
+        conn.execute_batch(MIGRATIONS[0])?;
+        conn.execute(
+            "INSERT INTO teams (id, name, abbreviation) VALUES (8, 'MTL', 'MTL');",
+            [],
+        )?;
+        migrate(&mut conn)?;
+        assert_eq!(version(&conn)?, SCHEMA_VERSION);
+        let teams: usize = conn.query_row("SELECT COUNT(*) FROM teams;", [], |row| row.get(0))?;
+        assert_eq!(teams, 1);
+        migrate(&mut conn)?;
+        assert_eq!(version(&conn)?, SCHEMA_VERSION);
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_newer_schemas() -> anyhow::Result<()> {
+        let mut conn = Connection::open_in_memory()?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)?;
+        assert!(migrate(&mut conn).is_err());
+        Ok(())
+    }
+}
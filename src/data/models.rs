@@ -1,8 +1,12 @@
 pub mod data;
+pub mod franchises;
 pub mod games;
+pub mod goals;
 pub mod head2head;
 pub mod last10;
 pub mod players;
 pub mod prediction;
 pub mod probability;
+pub mod scores;
+pub mod strength;
 pub mod teams;
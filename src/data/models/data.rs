@@ -1,12 +1,12 @@
-use serde::{Deserialize, Serialize};
+use anyhow::Context;
+use csv::StringRecord;
 
 use crate::data::models::prediction::Prediction;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// One side of a game as every model saw it, in the order of their columns
+#[derive(Debug, Clone, PartialEq)]
 pub struct Data {
-    pub rank_score: f64,
-    pub hist_score: f64,
-    pub la10_score: f64,
+    pub scores: Vec<f64>,
     pub outcome: u8,
 }
 
@@ -16,18 +16,13 @@ pub struct DataPackage {
 }
 
 impl DataPackage {
-    pub fn new(predictions: [Prediction; 3], outcome: u8) -> Self {
-        let [rank, hist, la10] = predictions;
+    pub fn new(predictions: &[Prediction], outcome: u8) -> Self {
         let away_data = Data {
-            rank_score: rank.exp_away,
-            hist_score: hist.exp_away,
-            la10_score: la10.exp_away,
+            scores: predictions.iter().map(|pred| pred.exp_away).collect(),
             outcome,
         };
         let home_data = Data {
-            rank_score: rank.exp_home,
-            hist_score: hist.exp_home,
-            la10_score: la10.exp_home,
+            scores: predictions.iter().map(|pred| pred.exp_home).collect(),
             outcome: outcome ^ 1,
         };
         DataPackage {
@@ -36,67 +31,105 @@ impl DataPackage {
         }
     }
 
-    pub fn serialize(&self) -> SerializableDataPackage {
-        SerializableDataPackage::new(
-            self.away_data.rank_score,
-            self.home_data.rank_score,
-            self.away_data.hist_score,
-            self.home_data.hist_score,
-            self.away_data.la10_score,
-            self.home_data.la10_score,
-            self.away_data.outcome,
-        )
+    /// Header of the exported scores of models with these columns, like
+    /// `away_rank,home_rank,away_hist,home_hist,outcome`
+    pub fn header(columns: &[&str]) -> Vec<String> {
+        columns
+            .iter()
+            .flat_map(|column| [format!("away_{column}"), format!("home_{column}")])
+            .chain(["outcome".to_string()])
+            .collect()
     }
-}
 
-#[derive(Serialize, Deserialize)]
-pub struct SerializableDataPackage {
-    pub away_rank: f64,
-    pub home_rank: f64,
-    pub away_hist: f64,
-    pub home_hist: f64,
-    pub away_la10: f64,
-    pub home_la10: f64,
-    pub outcome: u8,
-}
+    /// The row to write under [`header`](Self::header)
+    pub fn record(&self) -> Vec<String> {
+        self.away_data
+            .scores
+            .iter()
+            .zip(&self.home_data.scores)
+            .flat_map(|(away, home)| [away.to_string(), home.to_string()])
+            .chain([self.away_data.outcome.to_string()])
+            .collect()
+    }
 
-impl SerializableDataPackage {
-    fn new(
-        away_rank: f64,
-        home_rank: f64,
-        away_hist: f64,
-        home_hist: f64,
-        away_la10: f64,
-        home_la10: f64,
-        outcome: u8,
-    ) -> Self {
-        Self {
-            away_rank,
-            home_rank,
-            away_hist,
-            home_hist,
-            away_la10,
-            home_la10,
-            outcome,
-        }
+    /// Columns of the models in a header written by [`header`](Self::header)
+    pub fn columns(header: &StringRecord) -> Vec<String> {
+        header
+            .iter()
+            .filter_map(|name| name.strip_prefix("away_"))
+            .map(String::from)
+            .collect()
     }
-}
 
-impl From<SerializableDataPackage> for DataPackage {
-    fn from(serdatapack: SerializableDataPackage) -> Self {
-        Self {
+    /// Reads back a row written under `header`
+    pub fn from_record(header: &StringRecord, record: &StringRecord) -> anyhow::Result<Self> {
+        let field = |name: &str| -> anyhow::Result<&str> {
+            header
+                .iter()
+                .position(|field| field == name)
+                .and_then(|i| record.get(i))
+                .with_context(|| format!("missing {name} column"))
+        };
+        let (mut away, mut home) = (vec![], vec![]);
+        for column in Self::columns(header) {
+            away.push(field(&format!("away_{column}"))?.parse()?);
+            home.push(field(&format!("home_{column}"))?.parse()?);
+        }
+        let outcome: u8 = field("outcome")?.parse()?;
+        Ok(Self {
             away_data: Data {
-                rank_score: serdatapack.away_rank,
-                hist_score: serdatapack.away_hist,
-                la10_score: serdatapack.away_la10,
-                outcome: serdatapack.outcome,
+                scores: away,
+                outcome,
             },
             home_data: Data {
-                rank_score: serdatapack.home_rank,
-                hist_score: serdatapack.home_hist,
-                la10_score: serdatapack.home_la10,
-                outcome: serdatapack.outcome ^ 1,
+                scores: home,
+                outcome: outcome ^ 1,
             },
-        }
+        })
+    }
+
+    /// Every row of an exported scores file, with the columns of the models
+    /// they hold
+    pub fn read_all(path: &str) -> anyhow::Result<(Vec<String>, Vec<Self>)> {
+        let mut reader = csv::Reader::from_path(path)?;
+        let header = reader.headers()?.clone();
+        let rows = reader
+            .records()
+            .map(|record| Self::from_record(&header, &record?))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok((Self::columns(&header), rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use skillratings::Outcomes;
+
+    use super::*;
+
+    #[test]
+    fn rows_read_back_under_their_header() -> anyhow::Result<()> {
+        let prediction = |exp_away: f64| Prediction {
+            exp_away,
+            exp_home: 1. - exp_away,
+            outcome: Outcomes::WIN,
+        };
+        let package = DataPackage::new(&[prediction(0.75), prediction(0.5)], 1);
+        let header = StringRecord::from(DataPackage::header(&["rank", "pois"]));
+        assert_eq!(
+            header.iter().collect::<Vec<_>>(),
+            [
+                "away_rank",
+                "home_rank",
+                "away_pois",
+                "home_pois",
+                "outcome"
+            ]
+        );
+        assert_eq!(DataPackage::columns(&header), ["rank", "pois"]);
+        let read = DataPackage::from_record(&header, &StringRecord::from(package.record()))?;
+        assert_eq!(read.away_data, package.away_data);
+        assert_eq!(read.home_data, package.home_data);
+        Ok(())
     }
 }
@@ -0,0 +1,159 @@
+use chrono::NaiveDate;
+use rusqlite::Row;
+
+use crate::data::db::TeamID;
+
+/// A stretch of time during which a team id went by a given name and tricode
+#[derive(Debug, Clone, PartialEq)]
+pub struct TeamEra {
+    pub team_id: TeamID,
+    pub franchise_id: Option<i64>,
+    pub name: String,
+    pub abbrev: String,
+    /// First day of the era, open ended when `None`
+    pub valid_from: Option<NaiveDate>,
+    /// Day the era ended, exclusive, open ended when `None`
+    pub valid_to: Option<NaiveDate>,
+}
+
+impl TeamEra {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.valid_from.is_none_or(|from| from <= date) && self.valid_to.is_none_or(|to| date < to)
+    }
+}
+
+/// `(team id, tricode, name, from, to)`
+type KnownEra = (
+    TeamID,
+    &'static str,
+    &'static str,
+    Option<&'static str>,
+    Option<&'static str>,
+);
+
+/// Relocations and renames the team endpoint can't tell apart. Eras change
+/// over on July 1st so that they line up with season boundaries.
+const KNOWN_ERAS: &[KnownEra] = &[
+    (
+        47,
+        "AFM",
+        "Atlanta Flames",
+        Some("1972-07-01"),
+        Some("1980-07-01"),
+    ),
+    (20, "CGY", "Calgary Flames", Some("1980-07-01"), None),
+    (
+        48,
+        "KCS",
+        "Kansas City Scouts",
+        Some("1974-07-01"),
+        Some("1976-07-01"),
+    ),
+    (
+        35,
+        "CLR",
+        "Colorado Rockies",
+        Some("1976-07-01"),
+        Some("1982-07-01"),
+    ),
+    (1, "NJD", "New Jersey Devils", Some("1982-07-01"), None),
+    (
+        31,
+        "MNS",
+        "Minnesota North Stars",
+        Some("1967-07-01"),
+        Some("1993-07-01"),
+    ),
+    (25, "DAL", "Dallas Stars", Some("1993-07-01"), None),
+    (
+        32,
+        "QUE",
+        "Quebec Nordiques",
+        Some("1979-07-01"),
+        Some("1995-07-01"),
+    ),
+    (21, "COL", "Colorado Avalanche", Some("1995-07-01"), None),
+    (
+        34,
+        "HFD",
+        "Hartford Whalers",
+        Some("1979-07-01"),
+        Some("1997-07-01"),
+    ),
+    (12, "CAR", "Carolina Hurricanes", Some("1997-07-01"), None),
+    (
+        33,
+        "WIN",
+        "Winnipeg Jets (1979)",
+        Some("1979-07-01"),
+        Some("1996-07-01"),
+    ),
+    (
+        27,
+        "PHX",
+        "Phoenix Coyotes",
+        Some("1996-07-01"),
+        Some("2014-07-01"),
+    ),
+    (
+        53,
+        "ARI",
+        "Arizona Coyotes",
+        Some("2014-07-01"),
+        Some("2024-07-01"),
+    ),
+    (
+        11,
+        "ATL",
+        "Atlanta Thrashers",
+        Some("1999-07-01"),
+        Some("2011-07-01"),
+    ),
+    (52, "WPG", "Winnipeg Jets", Some("2011-07-01"), None),
+    (
+        24,
+        "MDA",
+        "Mighty Ducks of Anaheim",
+        Some("1993-07-01"),
+        Some("2006-07-01"),
+    ),
+    (24, "ANA", "Anaheim Ducks", Some("2006-07-01"), None),
+    (
+        59,
+        "UTA",
+        "Utah Hockey Club",
+        Some("2024-07-01"),
+        Some("2025-07-01"),
+    ),
+    (68, "UTA", "Utah Mammoth", Some("2025-07-01"), None),
+];
+
+/// The eras in [`KNOWN_ERAS`], with their franchise looked up by team id
+pub fn known_eras(franchise_of: impl Fn(TeamID) -> Option<i64>) -> Vec<TeamEra> {
+    let date = |date: Option<&str>| date.map(|date| date.parse().unwrap());
+    KNOWN_ERAS
+        .iter()
+        .map(|&(team_id, abbrev, name, from, to)| TeamEra {
+            team_id,
+            franchise_id: franchise_of(team_id),
+            name: name.to_string(),
+            abbrev: abbrev.to_string(),
+            valid_from: date(from),
+            valid_to: date(to),
+        })
+        .collect()
+}
+
+impl TryFrom<&Row<'_>> for TeamEra {
+    type Error = rusqlite::Error;
+    fn try_from(row: &Row<'_>) -> Result<Self, Self::Error> {
+        Ok(TeamEra {
+            team_id: row.get(0)?,
+            franchise_id: row.get(1)?,
+            name: row.get(2)?,
+            abbrev: row.get(3)?,
+            valid_from: row.get(4)?,
+            valid_to: row.get(5)?,
+        })
+    }
+}
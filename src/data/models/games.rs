@@ -1,38 +1,150 @@
-use nhl_api::{Boxscore, GameScore, ScheduleGame};
+use chrono::NaiveDate;
+use nhl_api::{Boxscore, GameType, PeriodType, ScheduleGame};
+use rusqlite::Row;
+use skillratings::Outcomes;
 
-use crate::data::{db::TeamID, models::teams::Team};
+use crate::{
+    data::{
+        db::TeamID,
+        models::{scores::ScoreGame, teams::Team},
+    },
+    rating::overtime::OvertimePolicy,
+};
 
+#[derive(Debug, Clone)]
 pub struct Game {
     pub id: i64,
+    pub date: NaiveDate,
+    /// Season id in the API's format, e.g. `20242025`
+    pub season: u32,
+    pub game_type: GameType,
     pub away_id: TeamID,
     pub home_id: TeamID,
     pub score: (u32, u32),
+    /// Period the game was decided in
+    pub resolution: PeriodType,
 }
 
 impl Game {
     pub fn ids(&self) -> (TeamID, TeamID) {
         (self.away_id, self.home_id)
     }
+
+    /// Whether the game went to overtime or a shootout
+    pub fn is_overtime(&self) -> bool {
+        self.resolution.is_overtime()
+    }
+
+    /// The result from the away team's side, scored with `policy`
+    pub fn result(&self, policy: OvertimePolicy) -> GameResult {
+        let (away_score, home_score) = self.score;
+        let outcome = match away_score.cmp(&home_score) {
+            std::cmp::Ordering::Greater => Outcomes::WIN,
+            std::cmp::Ordering::Less => Outcomes::LOSS,
+            std::cmp::Ordering::Equal => Outcomes::DRAW,
+        };
+        let overtime = self.is_overtime();
+        GameResult {
+            outcome,
+            overtime,
+            margin: away_score.abs_diff(home_score),
+            score: policy.score(outcome, overtime),
+            goals: self.score,
+        }
+    }
 }
 
-impl From<&ScheduleGame> for Game {
-    fn from(game: &ScheduleGame) -> Self {
+/// How a game ended, from the away team's side
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameResult {
+    /// Who won, `DRAW` for the ties of the pre-shootout era
+    pub outcome: Outcomes,
+    /// Decided in overtime or a shootout
+    pub overtime: bool,
+    pub margin: u32,
+    /// The away team's share of the result, one for a full win
+    pub score: f64,
+    /// Goals for and against
+    pub goals: (u32, u32),
+}
+
+impl GameResult {
+    /// Goals for and against at the end of regulation, leaving out the goal
+    /// that decided an overtime or shootout
+    pub fn regulation_goals(&self) -> (u32, u32) {
+        let (scored, conceded) = self.goals;
+        if self.overtime {
+            let tied = scored.min(conceded);
+            (tied, tied)
+        } else {
+            (scored, conceded)
+        }
+    }
+
+    pub fn flip(&self) -> GameResult {
+        GameResult {
+            outcome: match self.outcome {
+                Outcomes::WIN => Outcomes::LOSS,
+                Outcomes::LOSS => Outcomes::WIN,
+                Outcomes::DRAW => Outcomes::DRAW,
+            },
+            score: 1. - self.score,
+            goals: (self.goals.1, self.goals.0),
+            ..*self
+        }
+    }
+}
+
+/// A one goal result in regulation
+impl From<Outcomes> for GameResult {
+    fn from(outcome: Outcomes) -> Self {
+        GameResult {
+            outcome,
+            overtime: false,
+            margin: 1,
+            score: OvertimePolicy::Binary.score(outcome, false),
+            goals: match outcome {
+                Outcomes::WIN => (1, 0),
+                Outcomes::LOSS => (0, 1),
+                Outcomes::DRAW => (0, 0),
+            },
+        }
+    }
+}
+
+/// Game ids start with the year the season started in, e.g. `2024020001`
+pub fn season_from_id(id: i64) -> u32 {
+    let start = (id / 1_000_000) as u32;
+    start * 10_000 + start + 1
+}
+
+fn parse_date(date: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(&date[..10], "%Y-%m-%d").unwrap()
+}
+
+impl From<&ScoreGame> for Game {
+    fn from(game: &ScoreGame) -> Self {
         let (away_team, home_team) = (&game.away_team, &game.home_team);
-        let score = (
-            away_team.score.unwrap() as u32,
-            home_team.score.unwrap() as u32,
-        );
+        let score = (away_team.score.unwrap(), home_team.score.unwrap());
         Game {
             id: game.id,
+            date: parse_date(&game.game_date),
+            season: game.season,
+            game_type: game.game_type,
             away_id: away_team.id,
             home_id: home_team.id,
             score,
+            resolution: game
+                .game_outcome
+                .as_ref()
+                .map_or(PeriodType::Regulation, |outcome| outcome.last_period_type),
         }
     }
 }
 
-impl From<&GameScore> for Game {
-    fn from(game: &GameScore) -> Self {
+/// The schedule doesn't say how a game ended, so these are taken as regulation
+impl From<&ScheduleGame> for Game {
+    fn from(game: &ScheduleGame) -> Self {
         let (away_team, home_team) = (&game.away_team, &game.home_team);
         let score = (
             away_team.score.unwrap() as u32,
@@ -40,9 +152,13 @@ impl From<&GameScore> for Game {
         );
         Game {
             id: game.id,
+            date: parse_date(game.game_date.as_ref().unwrap_or(&game.start_time_utc)),
+            season: season_from_id(game.id),
+            game_type: game.game_type,
             away_id: away_team.id,
             home_id: home_team.id,
             score,
+            resolution: PeriodType::Regulation,
         }
     }
 }
@@ -53,9 +169,40 @@ impl From<&Boxscore> for Game {
         let score = (away_team.score as u32, home_team.score as u32);
         Game {
             id: game.id,
+            date: parse_date(&game.game_date),
+            season: game.season as u32,
+            game_type: game.game_type,
             away_id: away_team.id,
             home_id: home_team.id,
             score,
+            resolution: game.period_descriptor.period_type,
         }
     }
 }
+
+impl TryFrom<&Row<'_>> for Game {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &Row<'_>) -> Result<Self, Self::Error> {
+        let game_type: i32 = row.get(3)?;
+        let resolution: String = row.get(8)?;
+        Ok(Game {
+            id: row.get(0)?,
+            date: row.get(1)?,
+            season: row.get(2)?,
+            game_type: GameType::from_int(game_type).ok_or(
+                rusqlite::Error::IntegralValueOutOfRange(3, game_type as i64),
+            )?,
+            away_id: row.get(4)?,
+            home_id: row.get(5)?,
+            score: (row.get(6)?, row.get(7)?),
+            resolution: resolution.parse().map_err(|err| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    8,
+                    rusqlite::types::Type::Text,
+                    Box::new(err),
+                )
+            })?,
+        })
+    }
+}
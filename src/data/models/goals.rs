@@ -0,0 +1,36 @@
+use std::collections::VecDeque;
+
+use crate::data::{
+    db::TeamID,
+    models::{games::GameResult, last10::HISTORY},
+};
+
+/// Goals for and against in a team's latest games, up to [`HISTORY`] of them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GoalForm {
+    pub id: TeamID,
+    /// Oldest first
+    pub games: VecDeque<(u32, u32)>,
+}
+
+impl GoalForm {
+    pub fn new(id: TeamID) -> Self {
+        Self {
+            id,
+            games: VecDeque::new(),
+        }
+    }
+
+    /// `result` is from this team's side
+    pub fn update(&mut self, result: &GameResult) {
+        self.games.push_back(result.goals);
+        if self.games.len() > HISTORY {
+            self.games.pop_front();
+        }
+    }
+
+    /// The last `n` games at most, most recent first
+    pub fn recent(&self, n: usize) -> impl Iterator<Item = &(u32, u32)> {
+        self.games.iter().rev().take(n)
+    }
+}
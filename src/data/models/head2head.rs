@@ -2,22 +2,32 @@ use rusqlite::Row;
 use serde::Deserialize;
 use skillratings::Outcomes;
 
-#[derive(Debug, Deserialize)]
+use crate::data::models::games::GameResult;
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct Head2Head {
     pub team1: u32,
     pub team2: u32,
     pub total_games: u32,
     pub team_wins: u32,
     pub team_win_freq: f64,
+    /// Overtime and shootout losses, plus ties
+    pub ot_losses: u32,
+    /// Sum of the results' scores, `team_win_freq` is its average
+    pub score: f64,
 }
 
 impl Head2Head {
-    pub fn update(&mut self, won: Outcomes) {
-        if won == Outcomes::WIN {
-            self.team_wins += 1;
+    /// `result` is from `team1`'s side
+    pub fn update(&mut self, result: &GameResult) {
+        match result.outcome {
+            Outcomes::WIN => self.team_wins += 1,
+            Outcomes::LOSS if !result.overtime => {}
+            _ => self.ot_losses += 1,
         }
         self.total_games += 1;
-        self.team_win_freq = self.team_wins as f64 / self.total_games as f64;
+        self.score += result.score;
+        self.team_win_freq = self.score / self.total_games as f64;
     }
 }
 
@@ -30,6 +40,8 @@ impl TryFrom<&Row<'_>> for Head2Head {
             total_games: row.get(2)?,
             team_wins: row.get(3)?,
             team_win_freq: row.get(4)?,
+            ot_losses: row.get(5)?,
+            score: row.get(6)?,
         })
     }
 }
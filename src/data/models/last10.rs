@@ -1,36 +1,56 @@
 use std::collections::VecDeque;
 
-use log::debug;
 use rusqlite::Row;
 use skillratings::Outcomes;
 
-#[derive(Debug)]
+use crate::data::models::games::GameResult;
+
+/// Most recent games kept per team, one bit each in the stored masks
+pub const HISTORY: usize = 32;
+
+/// A team's recent form: the results of up to [`HISTORY`] of its latest
+/// games, with its record over the last 10 of them
+#[derive(Debug, Clone)]
 pub struct Last10 {
     pub id: u32,
     pub wins: u32,
     pub loss: u32,
-    pub games: VecDeque<Outcomes>,
+    /// Overtime and shootout losses, plus ties
+    pub otl: u32,
+    /// Result of each game and whether it went past regulation, oldest first
+    pub games: VecDeque<(Outcomes, bool)>,
+    /// Games the team has played in all
+    pub played: u32,
 }
 
 impl Last10 {
-    pub fn update(&mut self, outcome: Outcomes) {
-        if outcome == Outcomes::WIN {
-            self.wins += 1;
-        } else {
-            self.loss += 1;
+    /// `result` is from this team's side
+    pub fn update(&mut self, result: &GameResult) {
+        self.games.push_back((result.outcome, result.overtime));
+        if self.games.len() > HISTORY {
+            self.games.pop_front();
         }
-        self.games.push_back(outcome);
-        if let Some(outcome_old) = self.games.pop_front() {
-            if outcome_old == Outcomes::WIN {
-                self.wins -= 1;
-            } else if self.loss > 0 {
-                self.loss -= 1;
+        self.played += 1;
+        self.recount();
+    }
+
+    /// Derives the last 10 games record from the games themselves, so the
+    /// two can't drift apart
+    fn recount(&mut self) {
+        let (mut wins, mut loss, mut otl) = (0, 0, 0);
+        for &(outcome, overtime) in self.recent(10) {
+            match outcome {
+                Outcomes::WIN => wins += 1,
+                Outcomes::LOSS if !overtime => loss += 1,
+                _ => otl += 1,
             }
         }
+        (self.wins, self.loss, self.otl) = (wins, loss, otl);
     }
 
-    pub fn estimate(&self) -> f64 {
-        self.wins as f64 / 10.
+    /// The last `n` games at most, most recent first
+    pub fn recent(&self, n: usize) -> impl Iterator<Item = &(Outcomes, bool)> {
+        self.games.iter().rev().take(n)
     }
 }
 
@@ -38,24 +58,31 @@ impl TryFrom<&Row<'_>> for Last10 {
     type Error = rusqlite::Error;
 
     fn try_from(row: &Row<'_>) -> Result<Self, Self::Error> {
-        let mut games = VecDeque::new();
         let int_games: u32 = row.get(3)?;
-        let mut i = 10;
-        while i >= 0 {
-            let game = (int_games >> i) & 1;
-            let outcome = if game == 1 {
-                Outcomes::WIN
-            } else {
-                Outcomes::LOSS
-            };
-            games.push_back(outcome);
-            i -= 1;
-        }
+        let overtime: u32 = row.get(5)?;
+        let ties: u32 = row.get(6)?;
+        let played: u32 = row.get(7)?;
+        // Bit 0 is the most recent game
+        let games = (0..(played as usize).min(HISTORY))
+            .rev()
+            .map(|i| {
+                let outcome = if (int_games >> i) & 1 == 1 {
+                    Outcomes::WIN
+                } else if (ties >> i) & 1 == 1 {
+                    Outcomes::DRAW
+                } else {
+                    Outcomes::LOSS
+                };
+                (outcome, (overtime >> i) & 1 == 1)
+            })
+            .collect();
         Ok(Last10 {
             id: row.get(0)?,
             wins: row.get(1)?,
             loss: row.get(2)?,
+            otl: row.get(4)?,
             games,
+            played,
         })
     }
 }
@@ -1,4 +1,24 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct DiscreteProb<const N: usize> {
     pub pmf: [f64; N],
     pub cdf: [f64; N],
 }
+
+impl<const N: usize> DiscreteProb<N> {
+    /// A Poisson distribution with mean `rate`, the chance of `N - 1` or more
+    /// in the last value
+    pub fn poisson(rate: f64) -> Self {
+        let mut pmf = [0.; N];
+        let mut cdf = [0.; N];
+        let (mut p, mut sum) = ((-rate).exp(), 0.);
+        for k in 0..N - 1 {
+            pmf[k] = p;
+            sum += p;
+            cdf[k] = sum;
+            p *= rate / (k + 1) as f64;
+        }
+        pmf[N - 1] = (1. - sum).max(0.);
+        cdf[N - 1] = 1.;
+        Self { pmf, cdf }
+    }
+}
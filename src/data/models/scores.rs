@@ -0,0 +1,39 @@
+use nhl_api::{GameOutcome, GameState, GameType};
+use serde::Deserialize;
+
+use crate::data::db::TeamID;
+
+/// The `score/{date}` endpoint, which unlike `nhl_api::DailyScores` also
+/// reports the date, season and final period of every game
+#[derive(Debug, Deserialize)]
+pub struct ScoresResponse {
+    #[serde(rename = "currentDate")]
+    pub current_date: String,
+    #[serde(default)]
+    pub games: Vec<ScoreGame>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScoreGame {
+    pub id: i64,
+    pub season: u32,
+    #[serde(rename = "gameType")]
+    pub game_type: GameType,
+    #[serde(rename = "gameDate")]
+    pub game_date: String,
+    #[serde(rename = "gameState")]
+    pub game_state: GameState,
+    #[serde(rename = "awayTeam")]
+    pub away_team: ScoreTeam,
+    #[serde(rename = "homeTeam")]
+    pub home_team: ScoreTeam,
+    #[serde(rename = "gameOutcome", default)]
+    pub game_outcome: Option<GameOutcome>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScoreTeam {
+    pub id: TeamID,
+    pub abbrev: String,
+    pub score: Option<u32>,
+}
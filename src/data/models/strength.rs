@@ -0,0 +1,22 @@
+use crate::data::db::TeamID;
+
+/// A team's scoring and goal prevention relative to the league, on the log
+/// scale of its scoring rate. Zero is league average for both.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Strength {
+    pub id: TeamID,
+    /// Added to the log of the team's expected goals
+    pub attack: f64,
+    /// Taken off the log of its opponents' expected goals
+    pub defence: f64,
+}
+
+impl Strength {
+    pub fn new(id: TeamID) -> Self {
+        Self {
+            id,
+            attack: 0.,
+            defence: 0.,
+        }
+    }
+}
@@ -4,7 +4,7 @@ use skillratings::weng_lin::WengLinRating;
 
 use crate::data::models::head2head::Head2Head;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Team {
     pub id: u32,
 
@@ -28,6 +28,8 @@ impl Team {
             total_games: 0,
             team_wins: 0,
             team_win_freq: 0.,
+            ot_losses: 0,
+            score: 0.,
         }
     }
 }
@@ -0,0 +1,637 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    rc::Rc,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use chrono::NaiveDate;
+use rusqlite::Result;
+
+use crate::{
+    data::{
+        db::{DataBase, TeamID},
+        memory::MemoryState,
+        models::{
+            games::Game, goals::GoalForm, head2head::Head2Head, last10::Last10, strength::Strength,
+        },
+    },
+    rating::system::{Rating, RatingSystem},
+};
+
+/// What the models read and write for each game. [`DataBase`] keeps it in
+/// SQLite, [`MemoryStore`] in plain maps that can be shared across threads.
+pub trait Storage {
+    /// A team's rating in `system`, the system's default before its first game
+    fn get_rating(&self, id: TeamID, system: RatingSystem) -> Result<Rating>;
+    fn update_rating(&self, id: TeamID, rating: Rating) -> Result<()>;
+    /// The record of `team1` against `team2`, from `team1`'s side
+    fn get_h2h(&self, team1: TeamID, team2: TeamID) -> Result<Head2Head>;
+    fn update_h2h(&self, h2h: Head2Head) -> Result<()>;
+    fn get_last10(&self, id: TeamID) -> Result<Last10>;
+    fn update_last10(&self, last10: Last10) -> Result<()>;
+    fn get_goal_form(&self, id: TeamID) -> Result<GoalForm>;
+    fn update_goal_form(&self, form: GoalForm) -> Result<()>;
+    fn get_strength(&self, id: TeamID) -> Result<Strength>;
+    fn update_strength(&self, strength: Strength) -> Result<()>;
+    /// Games `away` hosted by `home` played before `as_of` (a date and game
+    /// id) from season `since` on, most recent first
+    fn meetings(
+        &self,
+        away: TeamID,
+        home: TeamID,
+        as_of: (NaiveDate, i64),
+        since: u32,
+        limit: Option<usize>,
+    ) -> Result<Vec<Game>>;
+}
+
+impl Storage for DataBase {
+    fn get_rating(&self, id: TeamID, system: RatingSystem) -> Result<Rating> {
+        DataBase::get_rating(self, id, system)
+    }
+
+    fn update_rating(&self, id: TeamID, rating: Rating) -> Result<()> {
+        DataBase::update_rating(self, id, rating)
+    }
+
+    fn get_h2h(&self, team1: TeamID, team2: TeamID) -> Result<Head2Head> {
+        DataBase::get_h2h(self, team1, team2)
+    }
+
+    fn update_h2h(&self, h2h: Head2Head) -> Result<()> {
+        DataBase::update_h2h(self, h2h)
+    }
+
+    fn get_last10(&self, id: TeamID) -> Result<Last10> {
+        DataBase::get_last10(self, id)
+    }
+
+    fn update_last10(&self, last10: Last10) -> Result<()> {
+        DataBase::update_last10(self, last10)
+    }
+
+    fn get_goal_form(&self, id: TeamID) -> Result<GoalForm> {
+        DataBase::get_goal_form(self, id)
+    }
+
+    fn update_goal_form(&self, form: GoalForm) -> Result<()> {
+        DataBase::update_goal_form(self, form)
+    }
+
+    fn get_strength(&self, id: TeamID) -> Result<Strength> {
+        DataBase::get_strength(self, id)
+    }
+
+    fn update_strength(&self, strength: Strength) -> Result<()> {
+        DataBase::update_strength(self, strength)
+    }
+
+    fn meetings(
+        &self,
+        away: TeamID,
+        home: TeamID,
+        as_of: (NaiveDate, i64),
+        since: u32,
+        limit: Option<usize>,
+    ) -> Result<Vec<Game>> {
+        DataBase::meetings(self, away, home, as_of, since, limit)
+    }
+}
+
+/// The games themselves and the bookkeeping around them that [`State`] and
+/// [`Backfill`] need next to the models' records: which games were processed,
+/// the seasons they fall in and the franchises' successions.
+///
+/// [`State`]: crate::model::state::State
+/// [`Backfill`]: crate::backfill::Backfill
+pub trait GameStore: Storage {
+    /// Runs `f` as a single unit of writes, joining the one already open
+    fn transaction<T, E>(
+        &self,
+        f: impl FnOnce() -> std::result::Result<T, E>,
+    ) -> std::result::Result<T, E>
+    where
+        E: From<rusqlite::Error>;
+    /// Stores a played game, returning whether it was new
+    fn add_game(&self, game: &Game) -> Result<bool>;
+    /// Every stored game in the order it was played
+    fn get_games(&self) -> Result<Vec<Game>>;
+    fn is_processed(&self, game_id: i64) -> Result<bool>;
+    fn mark_processed(&self, game_id: i64) -> Result<()>;
+    /// Last day the backfill went through completely
+    fn get_checkpoint(&self) -> Result<Option<NaiveDate>>;
+    fn set_checkpoint(&self, date: NaiveDate) -> Result<()>;
+    /// Writes back whatever is held apart from the stored tables
+    fn snapshot(&self) -> Result<()>;
+    /// Puts the models' records back to their defaults and forgets which
+    /// games were processed, keeping the games
+    fn reset_state(&self) -> Result<()>;
+    /// Latest season with a processed game
+    fn last_processed_season(&self) -> Result<Option<u32>>;
+    /// Every team with a stored game in `season`
+    fn season_teams(&self, season: u32) -> Result<Vec<TeamID>>;
+    /// Teams that took over from another team of the same franchise, as
+    /// `(predecessor, successor, date)` in the order they happened
+    fn successions(&self) -> Result<Vec<(TeamID, TeamID, NaiveDate)>>;
+    /// Hands `from`'s ratings and head to head records over to `to`
+    fn carry_over(&self, from: TeamID, to: TeamID) -> Result<()>;
+}
+
+impl GameStore for DataBase {
+    fn transaction<T, E>(
+        &self,
+        f: impl FnOnce() -> std::result::Result<T, E>,
+    ) -> std::result::Result<T, E>
+    where
+        E: From<rusqlite::Error>,
+    {
+        DataBase::transaction(self, f)
+    }
+
+    fn add_game(&self, game: &Game) -> Result<bool> {
+        DataBase::add_game(self, game)
+    }
+
+    fn get_games(&self) -> Result<Vec<Game>> {
+        DataBase::get_games(self)
+    }
+
+    fn is_processed(&self, game_id: i64) -> Result<bool> {
+        DataBase::is_processed(self, game_id)
+    }
+
+    fn mark_processed(&self, game_id: i64) -> Result<()> {
+        DataBase::mark_processed(self, game_id)
+    }
+
+    fn get_checkpoint(&self) -> Result<Option<NaiveDate>> {
+        DataBase::get_checkpoint(self)
+    }
+
+    fn set_checkpoint(&self, date: NaiveDate) -> Result<()> {
+        DataBase::set_checkpoint(self, date)
+    }
+
+    fn snapshot(&self) -> Result<()> {
+        DataBase::snapshot(self)
+    }
+
+    fn reset_state(&self) -> Result<()> {
+        DataBase::reset_state(self)
+    }
+
+    fn last_processed_season(&self) -> Result<Option<u32>> {
+        DataBase::last_processed_season(self)
+    }
+
+    fn season_teams(&self, season: u32) -> Result<Vec<TeamID>> {
+        DataBase::season_teams(self, season)
+    }
+
+    fn successions(&self) -> Result<Vec<(TeamID, TeamID, NaiveDate)>> {
+        DataBase::successions(self)
+    }
+
+    fn carry_over(&self, from: TeamID, to: TeamID) -> Result<()> {
+        DataBase::carry_over(self, from, to)
+    }
+}
+
+/// Forwards every call to the storage behind a pointer, so models can
+/// borrow it or share it
+macro_rules! forward_storage {
+    ($($ptr:ty),*) => {$(
+        impl<S: Storage + ?Sized> Storage for $ptr {
+            fn get_rating(&self, id: TeamID, system: RatingSystem) -> Result<Rating> {
+                (**self).get_rating(id, system)
+            }
+
+            fn update_rating(&self, id: TeamID, rating: Rating) -> Result<()> {
+                (**self).update_rating(id, rating)
+            }
+
+            fn get_h2h(&self, team1: TeamID, team2: TeamID) -> Result<Head2Head> {
+                (**self).get_h2h(team1, team2)
+            }
+
+            fn update_h2h(&self, h2h: Head2Head) -> Result<()> {
+                (**self).update_h2h(h2h)
+            }
+
+            fn get_last10(&self, id: TeamID) -> Result<Last10> {
+                (**self).get_last10(id)
+            }
+
+            fn update_last10(&self, last10: Last10) -> Result<()> {
+                (**self).update_last10(last10)
+            }
+
+            fn get_goal_form(&self, id: TeamID) -> Result<GoalForm> {
+                (**self).get_goal_form(id)
+            }
+
+            fn update_goal_form(&self, form: GoalForm) -> Result<()> {
+                (**self).update_goal_form(form)
+            }
+
+            fn get_strength(&self, id: TeamID) -> Result<Strength> {
+                (**self).get_strength(id)
+            }
+
+            fn update_strength(&self, strength: Strength) -> Result<()> {
+                (**self).update_strength(strength)
+            }
+
+            fn meetings(
+                &self,
+                away: TeamID,
+                home: TeamID,
+                as_of: (NaiveDate, i64),
+                since: u32,
+                limit: Option<usize>,
+            ) -> Result<Vec<Game>> {
+                (**self).meetings(away, home, as_of, since, limit)
+            }
+        }
+
+        impl<S: GameStore + ?Sized> GameStore for $ptr {
+            fn transaction<T, E>(
+                &self,
+                f: impl FnOnce() -> std::result::Result<T, E>,
+            ) -> std::result::Result<T, E>
+            where
+                E: From<rusqlite::Error>,
+            {
+                (**self).transaction(f)
+            }
+
+            fn add_game(&self, game: &Game) -> Result<bool> {
+                (**self).add_game(game)
+            }
+
+            fn get_games(&self) -> Result<Vec<Game>> {
+                (**self).get_games()
+            }
+
+            fn is_processed(&self, game_id: i64) -> Result<bool> {
+                (**self).is_processed(game_id)
+            }
+
+            fn mark_processed(&self, game_id: i64) -> Result<()> {
+                (**self).mark_processed(game_id)
+            }
+
+            fn get_checkpoint(&self) -> Result<Option<NaiveDate>> {
+                (**self).get_checkpoint()
+            }
+
+            fn set_checkpoint(&self, date: NaiveDate) -> Result<()> {
+                (**self).set_checkpoint(date)
+            }
+
+            fn snapshot(&self) -> Result<()> {
+                (**self).snapshot()
+            }
+
+            fn reset_state(&self) -> Result<()> {
+                (**self).reset_state()
+            }
+
+            fn last_processed_season(&self) -> Result<Option<u32>> {
+                (**self).last_processed_season()
+            }
+
+            fn season_teams(&self, season: u32) -> Result<Vec<TeamID>> {
+                (**self).season_teams(season)
+            }
+
+            fn successions(&self) -> Result<Vec<(TeamID, TeamID, NaiveDate)>> {
+                (**self).successions()
+            }
+
+            fn carry_over(&self, from: TeamID, to: TeamID) -> Result<()> {
+                (**self).carry_over(from, to)
+            }
+        }
+    )*};
+}
+
+forward_storage!(&S, Rc<S>, Arc<S>);
+
+/// Storage without SQLite, for tests and for models that outlive a
+/// connection. Records start out empty the first time they're read, and
+/// meetings come from the games handed to [`add_game`](GameStore::add_game).
+#[derive(Debug, Default)]
+pub struct MemoryStore(Mutex<Tables>);
+
+#[derive(Debug, Default)]
+struct Tables {
+    state: MemoryState,
+    /// Keyed by date and id, the order they were played in
+    games: BTreeMap<(NaiveDate, i64), Game>,
+    successions: Vec<(TeamID, TeamID, NaiveDate)>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `to` took over from `from` on `date`, for
+    /// [`successions`](GameStore::successions)
+    pub fn add_succession(&self, from: TeamID, to: TeamID, date: NaiveDate) {
+        let mut tables = self.tables();
+        tables.successions.push((from, to, date));
+        tables.successions.sort_by_key(|&(.., date)| date);
+    }
+
+    fn tables(&self) -> MutexGuard<'_, Tables> {
+        // Every update is a single insert, so a panicking holder can't leave
+        // the maps half written
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Storage for MemoryStore {
+    fn get_rating(&self, id: TeamID, system: RatingSystem) -> Result<Rating> {
+        Ok(self
+            .tables()
+            .state
+            .ratings
+            .get(&(id, system))
+            .copied()
+            .unwrap_or_else(|| system.new_rating()))
+    }
+
+    fn update_rating(&self, id: TeamID, rating: Rating) -> Result<()> {
+        self.tables()
+            .state
+            .ratings
+            .insert((id, rating.system()), rating);
+        Ok(())
+    }
+
+    fn get_h2h(&self, team1: TeamID, team2: TeamID) -> Result<Head2Head> {
+        Ok(self
+            .tables()
+            .state
+            .h2h
+            .get(&(team1, team2))
+            .cloned()
+            .unwrap_or(Head2Head {
+                team1: team1 as u32,
+                team2: team2 as u32,
+                total_games: 0,
+                team_wins: 0,
+                team_win_freq: 0.,
+                ot_losses: 0,
+                score: 0.,
+            }))
+    }
+
+    fn update_h2h(&self, h2h: Head2Head) -> Result<()> {
+        let key = (h2h.team1.into(), h2h.team2.into());
+        self.tables().state.h2h.insert(key, h2h);
+        Ok(())
+    }
+
+    fn get_last10(&self, id: TeamID) -> Result<Last10> {
+        Ok(self
+            .tables()
+            .state
+            .last10
+            .get(&id)
+            .cloned()
+            .unwrap_or(Last10 {
+                id: id as u32,
+                wins: 0,
+                loss: 0,
+                otl: 0,
+                games: VecDeque::new(),
+                played: 0,
+            }))
+    }
+
+    fn update_last10(&self, last10: Last10) -> Result<()> {
+        self.tables().state.last10.insert(last10.id.into(), last10);
+        Ok(())
+    }
+
+    fn get_goal_form(&self, id: TeamID) -> Result<GoalForm> {
+        Ok(self
+            .tables()
+            .state
+            .goal_form
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| GoalForm::new(id)))
+    }
+
+    fn update_goal_form(&self, form: GoalForm) -> Result<()> {
+        self.tables().state.goal_form.insert(form.id, form);
+        Ok(())
+    }
+
+    fn get_strength(&self, id: TeamID) -> Result<Strength> {
+        Ok(self
+            .tables()
+            .state
+            .strengths
+            .get(&id)
+            .copied()
+            .unwrap_or_else(|| Strength::new(id)))
+    }
+
+    fn update_strength(&self, strength: Strength) -> Result<()> {
+        self.tables().state.strengths.insert(strength.id, strength);
+        Ok(())
+    }
+
+    fn meetings(
+        &self,
+        away: TeamID,
+        home: TeamID,
+        as_of: (NaiveDate, i64),
+        since: u32,
+        limit: Option<usize>,
+    ) -> Result<Vec<Game>> {
+        Ok(self
+            .tables()
+            .games
+            .range(..as_of)
+            .rev()
+            .map(|(_, game)| game)
+            .filter(|game| game.away_id == away && game.home_id == home && game.season >= since)
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+}
+
+impl GameStore for MemoryStore {
+    /// Writes land in the maps straight away, so there is nothing to commit
+    /// and a failing `f` keeps what it wrote
+    fn transaction<T, E>(
+        &self,
+        f: impl FnOnce() -> std::result::Result<T, E>,
+    ) -> std::result::Result<T, E>
+    where
+        E: From<rusqlite::Error>,
+    {
+        f()
+    }
+
+    fn add_game(&self, game: &Game) -> Result<bool> {
+        let mut tables = self.tables();
+        let key = (game.date, game.id);
+        if tables.games.contains_key(&key) {
+            return Ok(false);
+        }
+        tables.games.insert(key, game.clone());
+        Ok(true)
+    }
+
+    fn get_games(&self) -> Result<Vec<Game>> {
+        Ok(self.tables().games.values().cloned().collect())
+    }
+
+    fn is_processed(&self, game_id: i64) -> Result<bool> {
+        Ok(self.tables().state.processed.contains(&game_id))
+    }
+
+    fn mark_processed(&self, game_id: i64) -> Result<()> {
+        self.tables().state.processed.insert(game_id);
+        Ok(())
+    }
+
+    fn get_checkpoint(&self) -> Result<Option<NaiveDate>> {
+        Ok(self.tables().state.checkpoint)
+    }
+
+    fn set_checkpoint(&self, date: NaiveDate) -> Result<()> {
+        self.tables().state.checkpoint = Some(date);
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn reset_state(&self) -> Result<()> {
+        let mut tables = self.tables();
+        tables.state = MemoryState {
+            checkpoint: tables.state.checkpoint,
+            ..MemoryState::default()
+        };
+        Ok(())
+    }
+
+    fn last_processed_season(&self) -> Result<Option<u32>> {
+        let tables = self.tables();
+        Ok(tables
+            .games
+            .values()
+            .filter(|game| tables.state.processed.contains(&game.id))
+            .map(|game| game.season)
+            .max())
+    }
+
+    fn season_teams(&self, season: u32) -> Result<Vec<TeamID>> {
+        Ok(self
+            .tables()
+            .games
+            .values()
+            .filter(|game| game.season == season)
+            .flat_map(|game| [game.away_id, game.home_id])
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect())
+    }
+
+    fn successions(&self) -> Result<Vec<(TeamID, TeamID, NaiveDate)>> {
+        Ok(self.tables().successions.clone())
+    }
+
+    fn carry_over(&self, from: TeamID, to: TeamID) -> Result<()> {
+        let mut tables = self.tables();
+        let state = &mut tables.state;
+        for system in RatingSystem::ALL {
+            if let Some(&rating) = state.ratings.get(&(from, system)) {
+                state.ratings.insert((to, system), rating);
+            }
+        }
+        let (from, to) = (from as u32, to as u32);
+        let inherited = state
+            .h2h
+            .values()
+            .filter(|h2h| h2h.team1 != to && h2h.team2 != to)
+            .filter_map(|h2h| {
+                if h2h.team1 == from {
+                    Some(Head2Head {
+                        team1: to,
+                        ..h2h.clone()
+                    })
+                } else if h2h.team2 == from {
+                    Some(Head2Head {
+                        team2: to,
+                        ..h2h.clone()
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        for h2h in inherited {
+            state.h2h.insert((h2h.team1.into(), h2h.team2.into()), h2h);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nhl_api::{GameType, PeriodType};
+    use skillratings::Outcomes;
+
+    use super::*;
+    use crate::{
+        data::models::games::GameResult,
+        model::{model::Model, poisson::PoissonModel},
+    };
+
+    #[test]
+    fn models_share_a_store_across_threads() -> anyhow::Result<()> {
+        let store = Arc::new(MemoryStore::new());
+        for year in 2019..2022 {
+            store.add_game(&Game {
+                id: 2019020001 + year as i64,
+                date: NaiveDate::from_ymd_opt(year, 11, 1).unwrap(),
+                season: year as u32 * 10001 + 1,
+                game_type: GameType::RegularSeason,
+                away_id: 8,
+                home_id: 10,
+                score: (4, 1),
+                resolution: PeriodType::Regulation,
+            })?;
+        }
+        let as_of = (NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(), 0);
+        let meetings = store.meetings(8, 10, as_of, 0, Some(2))?;
+        assert_eq!(meetings.len(), 2);
+        assert!(meetings[0].date > meetings[1].date);
+        assert!(store.meetings(10, 8, as_of, 0, None)?.is_empty());
+
+        let mut model = PoissonModel::from(Arc::clone(&store));
+        let worker = std::thread::spawn(move || -> rusqlite::Result<PoissonModel<_>> {
+            let mut result = GameResult::from(Outcomes::WIN);
+            result.goals = (6, 1);
+            for _ in 0..5 {
+                model.predict_and_update_result(8, 10, &result)?;
+            }
+            Ok(model)
+        });
+        let model = worker.join().unwrap()?;
+        assert!(store.get_strength(8)?.attack > 0.);
+        assert!(model.predict(8, 10)?.exp_away > 0.5);
+        Ok(())
+    }
+}
@@ -1,5 +1,14 @@
+pub mod backtest;
+pub mod calibration;
+pub mod calibrator;
+pub mod goals;
 pub mod historical;
 pub mod last10;
+pub mod metrics;
+#[allow(clippy::module_inception)]
 pub mod model;
+pub mod poisson;
 pub mod ranker;
+pub mod registry;
+pub mod seasons;
 pub mod state;
@@ -0,0 +1,145 @@
+use crate::{
+    data::{db::DataBase, models::games::Game},
+    model::{
+        metrics::Scores, model::Model, ranker::RankingModel, seasons::Seasons, state::StateConfig,
+    },
+    rating::overtime::OvertimePolicy,
+    utils::season_of,
+};
+
+/// One configuration of the ranker going through the backtest, on its own
+/// database so its ratings don't mix with the other runs'
+pub struct Run<'a> {
+    pub label: String,
+    db: &'a DataBase,
+    ranker: RankingModel<&'a DataBase>,
+    seasons: Seasons,
+    overtime: OvertimePolicy,
+    pub scores: Scores,
+}
+
+impl<'a> Run<'a> {
+    /// `db` should start from fresh ratings, like a [`DataBase::scratch`] copy
+    pub fn new(label: impl Into<String>, db: &'a DataBase, config: StateConfig) -> Self {
+        Self {
+            label: label.into(),
+            db,
+            ranker: RankingModel::with_config(db, config.ranker),
+            seasons: Seasons::default(),
+            overtime: config.overtime,
+            scores: Scores::new(config.window),
+        }
+    }
+
+    fn play(&mut self, game: &Game) -> rusqlite::Result<()> {
+        let ranker = &mut self.ranker;
+        self.seasons
+            .enter(self.db, game, ranker.config.carry_over, |teams| {
+                ranker.new_season(teams)
+            })?;
+        let (away, home) = game.ids();
+        let result = game.result(self.overtime);
+        let prediction = self.ranker.predict_and_update_result(away, home, &result)?;
+        self.scores
+            .record(season_of(game), &prediction, result.outcome);
+        Ok(())
+    }
+}
+
+/// Replays the same games through several differently configured rankers,
+/// scoring each prediction before the game is applied
+#[derive(Default)]
+pub struct Backtest<'a> {
+    pub runs: Vec<Run<'a>>,
+}
+
+impl<'a> Backtest<'a> {
+    pub fn add(&mut self, run: Run<'a>) {
+        self.runs.push(run);
+    }
+
+    /// Feeds `games` to every run in the order given
+    pub fn run(&mut self, games: &[Game]) -> rusqlite::Result<()> {
+        for run in &mut self.runs {
+            let db = run.db;
+            db.transaction(|| -> rusqlite::Result<()> {
+                for game in games {
+                    run.play(game)?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Seasons any run has a game in
+    pub fn seasons(&self) -> Vec<u32> {
+        let mut seasons = self
+            .runs
+            .iter()
+            .flat_map(|run| run.scores.by_season.keys().copied())
+            .collect::<Vec<_>>();
+        seasons.sort_unstable();
+        seasons.dedup();
+        seasons
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use nhl_api::{GameType, PeriodType};
+
+    use super::*;
+    use crate::{model::ranker::RankerConfig, rating::system::RatingSystem};
+
+    #[test]
+    fn runs_are_independent() -> anyhow::Result<()> {
+        let db = DataBase::new(":memory:")?;
+        db.add_team(8, "Montréal Canadiens".to_string(), "MTL".to_string())?;
+        db.add_team(10, "Toronto Maple Leafs".to_string(), "TOR".to_string())?;
+        let games = [(2022, (4, 1)), (2023, (2, 3)), (2023, (5, 2))]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (year, score))| Game {
+                id: i as i64 + 1,
+                date: NaiveDate::from_ymd_opt(year, 11, 1 + i as u32).unwrap(),
+                season: year as u32 * 10001 + 1,
+                game_type: GameType::RegularSeason,
+                away_id: 8,
+                home_id: 10,
+                score,
+                resolution: PeriodType::Regulation,
+            })
+            .collect::<Vec<_>>();
+
+        let scratch = [db.scratch()?, db.scratch()?, db.scratch()?];
+        let mut backtest = Backtest::default();
+        for (db, system) in scratch.iter().zip([
+            RatingSystem::WengLin,
+            RatingSystem::WengLin,
+            RatingSystem::Elo,
+        ]) {
+            let config = StateConfig {
+                ranker: RankerConfig {
+                    system,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            backtest.add(Run::new(system.name(), db, config));
+        }
+        backtest.run(&games)?;
+
+        let [one, two, elo] = &backtest.runs[..] else {
+            unreachable!()
+        };
+        assert_eq!(one.scores.total, two.scores.total);
+        assert_eq!(elo.scores.total.games, 3);
+        assert_eq!(backtest.seasons(), [20222023, 20232024]);
+        assert_eq!(one.scores.by_season[&20232024].games, 2);
+        assert!(one.scores.total.log_loss() > 0.);
+        assert_eq!(db.get_team(8)?.rating.rating, 25.0);
+        Ok(())
+    }
+}
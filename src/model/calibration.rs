@@ -0,0 +1,115 @@
+use skillratings::Outcomes;
+
+use crate::{model::metrics::actual_score, utils::EPSILON};
+
+/// Bins in the calibration reports unless asked otherwise
+pub const BINS: usize = 10;
+
+/// Games whose favourite was given about the same probability
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Bin {
+    pub games: usize,
+    /// Average probability given to the favourite
+    pub predicted: f64,
+    /// How often the favourite actually won, pick'ems counting half
+    pub observed: f64,
+}
+
+/// Predicted against observed win rates of a model's favourites
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Calibration {
+    /// Equally wide bins over the favourite's probability from 0.5 to 1,
+    /// the empty ones left out
+    pub bins: Vec<Bin>,
+    /// Expected calibration error, the gap between predicted and observed
+    /// averaged over the games
+    pub ece: f64,
+    pub games: usize,
+}
+
+impl Calibration {
+    /// Builds the report from the away win probability and outcome of every
+    /// game. Ties count as half a win for the favourite, like they count as
+    /// half in the metrics.
+    pub fn from_samples(samples: &[(f64, Outcomes)], nbins: usize) -> Self {
+        let nbins = nbins.max(1);
+        let mut sums = vec![(0usize, 0., 0.); nbins];
+        for &(exp_away, outcome) in samples {
+            let score = actual_score(outcome);
+            let (favourite, won) = if (exp_away - 0.5).abs() < EPSILON {
+                (0.5, 0.5)
+            } else if exp_away > 0.5 {
+                (exp_away, score)
+            } else {
+                (1. - exp_away, 1. - score)
+            };
+            let bin = (((favourite - 0.5) * 2. * nbins as f64) as usize).min(nbins - 1);
+            let (games, predicted, wins) = &mut sums[bin];
+            *games += 1;
+            *predicted += favourite;
+            *wins += won;
+        }
+        let bins = sums
+            .into_iter()
+            .filter(|&(games, ..)| games > 0)
+            .map(|(games, predicted, wins)| Bin {
+                games,
+                predicted: predicted / games as f64,
+                observed: wins / games as f64,
+            })
+            .collect::<Vec<_>>();
+        let games = bins.iter().map(|bin| bin.games).sum::<usize>();
+        let ece = if games == 0 {
+            0.
+        } else {
+            bins.iter()
+                .map(|bin| bin.games as f64 * (bin.observed - bin.predicted).abs())
+                .sum::<f64>()
+                / games as f64
+        };
+        Self { bins, ece, games }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mirrors_upsets_into_the_favourites_bin() {
+        // Favourites at 70% won 7 games and lost 3, from either side
+        let mut samples = vec![(0.7, Outcomes::WIN); 4];
+        samples.extend([(0.3, Outcomes::LOSS); 3]);
+        samples.extend([(0.7, Outcomes::LOSS); 2]);
+        samples.push((0.3, Outcomes::WIN));
+        samples.extend([(0.5, Outcomes::WIN), (0.5, Outcomes::LOSS)]);
+        let calibration = Calibration::from_samples(&samples, 5);
+        assert_eq!(calibration.games, 12);
+        assert_eq!(
+            calibration.bins[0],
+            Bin {
+                games: 2,
+                predicted: 0.5,
+                observed: 0.5
+            }
+        );
+        let bin = calibration.bins[1];
+        assert_eq!(bin.games, 10);
+        assert!((bin.predicted - 0.7).abs() < 1e-12);
+        assert!((bin.observed - 0.7).abs() < 1e-12);
+        assert!(calibration.ece < 1e-12);
+    }
+
+    #[test]
+    fn ties_count_half_for_the_favourite() {
+        let samples = [
+            (0.8, Outcomes::DRAW),
+            (0.2, Outcomes::DRAW),
+            (0.8, Outcomes::WIN),
+            (0.2, Outcomes::LOSS),
+        ];
+        let calibration = Calibration::from_samples(&samples, 5);
+        assert_eq!(calibration.bins.len(), 1);
+        assert!((calibration.bins[0].observed - 0.75).abs() < 1e-12);
+    }
+}
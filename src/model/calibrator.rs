@@ -0,0 +1,287 @@
+use clap::ValueEnum;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use skillratings::Outcomes;
+
+use crate::{
+    data::{
+        db::{DataBase, TeamID},
+        models::{games::GameResult, prediction::Prediction},
+    },
+    model::{
+        metrics::away_prob,
+        model::{GameModel, Model},
+    },
+    utils::outcome_from_prob,
+};
+
+/// Newton steps taken when fitting a Platt scaling
+const PLATT_STEPS: usize = 100;
+
+/// Keeps the logit finite for probabilities of exactly 0 or 1
+const CLAMP: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum CalibrationMethod {
+    /// A logistic curve over the model's log odds
+    #[default]
+    Platt,
+    /// The best non-decreasing step function, pool adjacent violators
+    Isotonic,
+}
+
+/// Maps a model's away win probability to a better calibrated one, fitted on
+/// `(predicted, actual)` pairs where ties score one half
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Calibrator {
+    /// `sigmoid(a * logit(p) + b)`
+    Platt { a: f64, b: f64 },
+    /// Points to interpolate between, sorted by the predicted probability
+    Isotonic { points: Vec<(f64, f64)> },
+}
+
+impl Calibrator {
+    pub fn fit(method: CalibrationMethod, samples: &[(f64, f64)]) -> Self {
+        match method {
+            CalibrationMethod::Platt => Self::fit_platt(samples),
+            CalibrationMethod::Isotonic => Self::fit_isotonic(samples),
+        }
+    }
+
+    /// Logistic regression of the outcomes on the log odds, by Newton's
+    /// method starting from the identity
+    pub fn fit_platt(samples: &[(f64, f64)]) -> Self {
+        let (mut a, mut b) = (1., 0.);
+        for _ in 0..PLATT_STEPS {
+            let (mut ga, mut gb, mut haa, mut hab, mut hbb) = (0., 0., 0., 0., 0.);
+            for &(p, y) in samples {
+                let x = logit(p);
+                let q = sigmoid(a * x + b);
+                let w = q * (1. - q);
+                ga += (q - y) * x;
+                gb += q - y;
+                haa += w * x * x;
+                hab += w * x;
+                hbb += w;
+            }
+            // A touch of ridge keeps the step defined on degenerate samples
+            let (haa, hbb) = (haa + 1e-9, hbb + 1e-9);
+            let det = haa * hbb - hab * hab;
+            if det.abs() < 1e-12 {
+                break;
+            }
+            let da = (hbb * ga - hab * gb) / det;
+            let db = (haa * gb - hab * ga) / det;
+            a -= da;
+            b -= db;
+            if da.abs().max(db.abs()) < 1e-10 {
+                break;
+            }
+        }
+        Calibrator::Platt { a, b }
+    }
+
+    /// Pools adjacent violators until the outcomes are non-decreasing in the
+    /// predicted probability
+    pub fn fit_isotonic(samples: &[(f64, f64)]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|(p1, _), (p2, _)| p1.total_cmp(p2));
+        // Each block holds its lowest and highest prediction, the total of
+        // its outcomes and how many samples it pools
+        let mut blocks: Vec<(f64, f64, f64, f64)> = vec![];
+        for (p, y) in sorted {
+            // Equal predictions can't be told apart, so they start pooled
+            match blocks.last_mut() {
+                Some(last) if last.1 == p => {
+                    last.2 += y;
+                    last.3 += 1.;
+                }
+                _ => blocks.push((p, p, y, 1.)),
+            }
+            while let [.., (_, _, y1, n1), (_, _, y2, n2)] = blocks[..]
+                && y1 / n1 >= y2 / n2
+            {
+                let (_, hi, y2, n2) = blocks.pop().unwrap();
+                let last = blocks.last_mut().unwrap();
+                last.1 = hi;
+                last.2 += y2;
+                last.3 += n2;
+            }
+        }
+        let mut points = vec![];
+        for (lo, hi, y, n) in blocks {
+            points.push((lo, y / n));
+            if hi > lo {
+                points.push((hi, y / n));
+            }
+        }
+        Calibrator::Isotonic { points }
+    }
+
+    /// The calibrated away win probability
+    pub fn apply(&self, p: f64) -> f64 {
+        match self {
+            Calibrator::Platt { a, b } => sigmoid(a * logit(p) + b),
+            Calibrator::Isotonic { points } => {
+                let Some(&(first, low)) = points.first() else {
+                    return p;
+                };
+                let &(last, high) = points.last().unwrap();
+                if p <= first {
+                    return low;
+                }
+                if p >= last {
+                    return high;
+                }
+                let i = points.partition_point(|&(x, _)| x <= p);
+                let ((x1, y1), (x2, y2)) = (points[i - 1], points[i]);
+                y1 + (p - x1) / (x2 - x1) * (y2 - y1)
+            }
+        }
+    }
+
+    pub fn calibrate(&self, prediction: Prediction) -> Prediction {
+        let exp_away = self.apply(away_prob(&prediction));
+        let exp_home = 1. - exp_away;
+        Prediction {
+            exp_away,
+            exp_home,
+            outcome: outcome_from_prob(exp_away, exp_home),
+        }
+    }
+}
+
+fn logit(p: f64) -> f64 {
+    let p = p.clamp(CLAMP, 1. - CLAMP);
+    (p / (1. - p)).ln()
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1. / (1. + (-x).exp())
+}
+
+/// A model whose predictions go through a calibrator, if one was fitted
+pub struct Calibrated<M> {
+    pub model: M,
+    pub calibrator: Option<Calibrator>,
+}
+
+impl<M> Calibrated<M> {
+    pub fn new(model: M, calibrator: Option<Calibrator>) -> Self {
+        Self { model, calibrator }
+    }
+
+    pub fn calibrate(&self, prediction: Prediction) -> Prediction {
+        match &self.calibrator {
+            Some(calibrator) => calibrator.calibrate(prediction),
+            None => prediction,
+        }
+    }
+}
+
+impl<M: GameModel> Calibrated<M> {
+    /// Wraps `model` with the calibrator `calibrate` stored for it, unless
+    /// that one was fitted to the model set up differently
+    pub fn stored(db: &DataBase, model: M) -> rusqlite::Result<Self> {
+        let key = model.calibrator_key();
+        let calibrator = match db.get_calibrator(&key)? {
+            Some((calibrator, settings)) if settings == model.settings() => Some(calibrator),
+            Some(_) => {
+                warn!("Leaving {key} uncalibrated, its calibrator was fitted to other settings");
+                None
+            }
+            None => None,
+        };
+        Ok(Self::new(model, calibrator))
+    }
+}
+
+impl<T, M: Model<T>> Model<T> for Calibrated<M> {
+    fn predict(
+        &self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+    ) -> rusqlite::Result<Prediction> {
+        self.model
+            .predict(away, home)
+            .map(|prediction| self.calibrate(prediction))
+    }
+
+    fn predict_and_get(
+        &self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+    ) -> rusqlite::Result<(T, T, Prediction)> {
+        let (away, home, prediction) = self.model.predict_and_get(away, home)?;
+        Ok((away, home, self.calibrate(prediction)))
+    }
+
+    fn update(
+        &mut self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+        outcome: Outcomes,
+    ) -> rusqlite::Result<()> {
+        self.model.update(away, home, outcome)
+    }
+
+    fn predict_and_update_result(
+        &mut self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+        result: &GameResult,
+    ) -> rusqlite::Result<Prediction> {
+        self.model
+            .predict_and_update_result(away, home, result)
+            .map(|prediction| self.calibrate(prediction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixes_overconfidence() {
+        // Favourites at 80% only win 60% of the time
+        let samples = [(0.8, 1.), (0.8, 1.), (0.8, 1.), (0.8, 0.), (0.8, 0.)]
+            .into_iter()
+            .chain([(0.2, 0.), (0.2, 0.), (0.2, 0.), (0.2, 1.), (0.2, 1.)])
+            .collect::<Vec<_>>();
+        for method in [CalibrationMethod::Platt, CalibrationMethod::Isotonic] {
+            let calibrator = Calibrator::fit(method, &samples);
+            assert!((calibrator.apply(0.8) - 0.6).abs() < 1e-6, "{calibrator:?}");
+            assert!((calibrator.apply(0.2) - 0.4).abs() < 1e-6, "{calibrator:?}");
+            let json = serde_json::to_string(&calibrator).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Calibrator>(&json).unwrap(),
+                calibrator
+            );
+        }
+        let isotonic = Calibrator::fit_isotonic(&[(0.1, 1.), (0.3, 0.), (0.9, 1.)]);
+        assert_eq!(isotonic.apply(0.2), 0.5);
+        assert_eq!(isotonic.apply(0.6), 0.75);
+    }
+
+    #[test]
+    fn only_applies_to_the_settings_fitted() -> anyhow::Result<()> {
+        use crate::model::historical::{HistoricalConfig, HistoricalMatchupModel};
+
+        let db = DataBase::new(":memory:")?;
+        let calibrator = Calibrator::fit_isotonic(&[(0.2, 0.), (0.8, 1.)]);
+        let model = HistoricalMatchupModel::from(&db);
+        db.set_calibrator(&model.calibrator_key(), &calibrator, 3, &model.settings())?;
+        let stored = Calibrated::stored(&db, model)?;
+        assert!(stored.calibrator.is_some());
+
+        let windowed = HistoricalMatchupModel::with_config(
+            &db,
+            HistoricalConfig {
+                meetings: Some(5),
+                ..Default::default()
+            },
+        );
+        assert_eq!(Calibrated::stored(&db, windowed)?.calibrator, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+use skillratings::Outcomes;
+
+use crate::{
+    data::{
+        db::{DataBase, TeamID},
+        models::{
+            games::{Game, GameResult},
+            goals::GoalForm,
+            last10::HISTORY,
+            prediction::Prediction,
+        },
+        storage::Storage,
+    },
+    model::model::{GameModel, Model, ModelBase},
+    utils::outcome_from_prob,
+};
+
+/// Goals a game is assumed to have on either side while a team has fewer
+/// games than the window, about the league's scoring rate
+const LEAGUE_GOALS: f64 = 3.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GoalFormConfig {
+    /// Games looked back at, at most [`HISTORY`]
+    pub window: usize,
+    /// Exponent of the Pythagorean expectation, around 2 for hockey
+    pub exponent: f64,
+}
+
+impl Default for GoalFormConfig {
+    fn default() -> Self {
+        Self {
+            window: 10,
+            exponent: 2.,
+        }
+    }
+}
+
+impl GoalFormConfig {
+    /// The share of games a team scoring and conceding like it did over the
+    /// window would win. Games it hasn't played yet count as even.
+    pub fn pythagorean(&self, form: &GoalForm) -> f64 {
+        let window = self.window.clamp(1, HISTORY);
+        let (mut goals_for, mut goals_against, mut games) = (0., 0., 0);
+        for &(scored, conceded) in form.recent(window) {
+            goals_for += scored as f64;
+            goals_against += conceded as f64;
+            games += 1;
+        }
+        let missing = (window - games) as f64 * LEAGUE_GOALS;
+        let goals_for = (goals_for + missing).powf(self.exponent);
+        let goals_against = (goals_against + missing).powf(self.exponent);
+        if goals_for + goals_against == 0. {
+            return 0.5;
+        }
+        goals_for / (goals_for + goals_against)
+    }
+}
+
+/// Chance that a team winning a share `one` of its games beats one winning
+/// `two`, by Bill James' log5
+pub fn log5(one: f64, two: f64) -> f64 {
+    let (win, loss) = (one * (1. - two), two * (1. - one));
+    if win + loss == 0. {
+        return 0.5;
+    }
+    win / (win + loss)
+}
+
+/// Pits the teams' Pythagorean expectations from their recent goals for and
+/// against
+pub type GoalFormModel<S> = ModelBase<S, [usize; 101], GoalFormConfig>;
+
+impl<S: Storage> From<S> for GoalFormModel<S> {
+    fn from(db: S) -> Self {
+        Self::with_config(db, GoalFormConfig::default())
+    }
+}
+
+impl<S: Storage> GoalFormModel<S> {
+    pub fn with_config(db: S, config: GoalFormConfig) -> Self {
+        Self {
+            db,
+            dist: [0; 101],
+            succ: 0,
+            config,
+        }
+    }
+
+    pub fn exp2idx(&self, exp: f64) -> usize {
+        (exp * (self.dist.len() as f64 - 1.)).round() as usize
+    }
+}
+
+impl<S: Storage> Model<GoalForm> for GoalFormModel<S> {
+    fn predict(
+        &self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+    ) -> rusqlite::Result<Prediction> {
+        self.predict_and_get(away, home).map(|(_, _, pred)| pred)
+    }
+
+    fn predict_and_get(
+        &self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+    ) -> rusqlite::Result<(GoalForm, GoalForm, Prediction)> {
+        let away = self.db.get_goal_form(away.into())?;
+        let home = self.db.get_goal_form(home.into())?;
+        let exp_away = log5(
+            self.config.pythagorean(&away),
+            self.config.pythagorean(&home),
+        );
+        let exp_home = 1. - exp_away;
+        let outcome = outcome_from_prob(exp_away, exp_home);
+        Ok((
+            away,
+            home,
+            Prediction {
+                exp_away,
+                exp_home,
+                outcome,
+            },
+        ))
+    }
+
+    fn update(
+        &mut self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+        outcome: Outcomes,
+    ) -> rusqlite::Result<()> {
+        self.predict_and_update(away, home, outcome).map(|_| ())
+    }
+
+    fn predict_and_update_result(
+        &mut self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+        result: &GameResult,
+    ) -> rusqlite::Result<Prediction> {
+        let (mut away, mut home, prediction) = self.predict_and_get(away, home)?;
+        let outcome = result.outcome;
+        let idx = self.exp2idx(if let Outcomes::WIN = outcome {
+            prediction.exp_away
+        } else {
+            prediction.exp_home
+        });
+        self.dist[idx] += 1;
+        away.update(result);
+        home.update(&result.flip());
+        self.db.update_goal_form(away)?;
+        self.db.update_goal_form(home)?;
+        if prediction.outcome == outcome {
+            self.succ += 1;
+        }
+        Ok(prediction)
+    }
+}
+
+/// Name of the goal form model in the metrics
+pub const LABEL: &str = "goal form";
+
+impl<S: Storage> GameModel for GoalFormModel<S> {
+    fn label(&self) -> &'static str {
+        LABEL
+    }
+
+    fn column(&self) -> &'static str {
+        "goal"
+    }
+
+    fn settings(&self) -> String {
+        format!("{:?}", self.config)
+    }
+
+    fn predict_game(
+        &self,
+        away: TeamID,
+        home: TeamID,
+        _earlier: &[(&'static str, Prediction)],
+    ) -> rusqlite::Result<Prediction> {
+        self.predict(away, home)
+    }
+
+    fn observe(
+        &mut self,
+        game: &Game,
+        result: &GameResult,
+        _earlier: &[(&'static str, Prediction)],
+    ) -> rusqlite::Result<Prediction> {
+        self.predict_and_update_result(game.away_id, game.home_id, result)
+    }
+
+    fn hits(&self) -> usize {
+        self.succ
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outscoring_teams_are_favoured() -> anyhow::Result<()> {
+        let db = DataBase::new(":memory:")?;
+        let mut model = GoalFormModel::from(&db);
+        assert_eq!(model.predict(8, 10)?.exp_away, 0.5);
+        for _ in 0..3 {
+            model.predict_and_update_result(8, 10, &GameResult::from(Outcomes::WIN))?;
+        }
+        let form = db.get_goal_form(8)?;
+        assert_eq!(form.games, [(1, 0); 3]);
+        // 7 games at 3-3 and three 1-0 wins
+        let expected = 24f64.powi(2) / (24f64.powi(2) + 21f64.powi(2));
+        assert!((model.config.pythagorean(&form) - expected).abs() < 1e-12);
+        let prediction = model.predict(8, 10)?;
+        assert!(prediction.exp_away > 0.5);
+        assert_eq!(prediction.outcome, Outcomes::WIN);
+        assert!((log5(0.6, 0.5) - 0.6).abs() < 1e-12);
+        Ok(())
+    }
+}
@@ -1,3 +1,7 @@
+use std::cmp::Reverse;
+
+use chrono::Local;
+use clap::ValueEnum;
 use log::debug;
 use skillratings::Outcomes;
 
@@ -5,29 +9,147 @@ use crate::{
     data::{
         db::{DataBase, TeamID},
         models::{
+            games::{Game, GameResult},
             head2head::Head2Head,
             prediction::Prediction,
             probability::DiscreteProb,
             teams::{self, Team},
         },
+        storage::Storage,
+    },
+    model::{
+        metrics::away_prob,
+        model::{GameModel, Model, ModelBase},
+        ranker,
     },
-    model::model::{Model, ModelBase},
-    utils::outcome_from_prob,
+    rating::overtime::OvertimePolicy,
+    utils::{outcome_from_prob, season_from_date, season_of},
 };
 
-pub type HistoricalMatchupModel<'a> = ModelBase<'a, [usize; 1001]>;
+/// Days in a year, for the half-life of the meetings
+const YEAR: f64 = 365.25;
+
+/// What a head to head record is shrunk toward while it has few games
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum HistoricalPrior {
+    /// The away teams' share of the results across the league
+    #[default]
+    League,
+    /// The ranker's probability for the game
+    Ranking,
+}
+
+/// Which of a matchup's meetings make up its record. The head to head
+/// counters hold every meeting wherever it was played, so only the stored
+/// games can tell the venues apart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Venues {
+    /// Every meeting as it was, wherever it was played
+    #[default]
+    Combined,
+    /// Only the away team's visits to the home team
+    Split,
+    /// Every meeting, taking the home edge off the away team's results at its
+    /// own building
+    Adjusted,
+}
+
+/// A matchup's meetings from the away team's side
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Record {
+    /// Weighted sum of the away team's results
+    pub score: f64,
+    /// Weighted number of meetings
+    pub games: f64,
+    /// Number of meetings counted, whatever their weight
+    pub meetings: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HistoricalConfig {
+    pub prior: HistoricalPrior,
+    /// Weight of the prior as a number of games, the raw record without any
+    pub prior_strength: f64,
+    /// Away teams' share of the results, the prior with
+    /// [`HistoricalPrior::League`]
+    pub baseline: f64,
+    /// Only the meetings of the last this many seasons count
+    pub seasons: Option<u32>,
+    /// Only the last this many meetings count
+    pub meetings: Option<usize>,
+    /// Years over which a meeting's weight halves
+    pub half_life: Option<f64>,
+    pub venues: Venues,
+    /// How meetings decided past regulation are scored when they are read
+    /// back from the stored games
+    pub overtime: OvertimePolicy,
+}
+
+impl Default for HistoricalConfig {
+    fn default() -> Self {
+        Self {
+            prior: HistoricalPrior::default(),
+            prior_strength: 10.,
+            baseline: 0.5,
+            seasons: None,
+            meetings: None,
+            half_life: None,
+            venues: Venues::default(),
+            overtime: OvertimePolicy::default(),
+        }
+    }
+}
+
+impl HistoricalConfig {
+    /// Mean of the Beta posterior of a record of `games` adding up to
+    /// `score`, starting from `prior_strength` games at `prior`
+    pub fn estimate(&self, score: f64, games: f64, prior: f64) -> f64 {
+        let games = games + self.prior_strength;
+        if games == 0. {
+            return prior;
+        }
+        // Venue adjusted results can stray slightly past either end
+        ((score + self.prior_strength * prior) / games).clamp(0., 1.)
+    }
+
+    /// Whether the record is rebuilt from the stored games rather than read
+    /// from the head to head counters
+    pub fn from_games(&self) -> bool {
+        self.seasons.is_some()
+            || self.meetings.is_some()
+            || self.half_life.is_some()
+            || self.venues != Venues::Combined
+    }
+}
+
+/// Scores the away team by its past results against the home team
+pub type HistoricalMatchupModel<S> = ModelBase<S, [usize; 1001], HistoricalConfig>;
+
+impl<S: Storage> From<S> for HistoricalMatchupModel<S> {
+    fn from(db: S) -> Self {
+        Self::with_config(db, HistoricalConfig::default())
+    }
+}
+
+impl HistoricalMatchupModel<&DataBase> {
+    /// The away teams' share of the results in the stored games, `None`
+    /// without any
+    pub fn fit_baseline(db: &DataBase) -> rusqlite::Result<Option<f64>> {
+        let (ngames, home_score) = db.home_record()?;
+        Ok((ngames > 0).then_some(1. - home_score))
+    }
+}
 
-impl<'a> From<&'a DataBase> for HistoricalMatchupModel<'a> {
-    fn from(db: &'a DataBase) -> Self {
+impl<S: Storage> HistoricalMatchupModel<S> {
+    pub fn with_config(db: S, config: HistoricalConfig) -> Self {
         Self {
             db,
             dist: [0; 1001],
             succ: 0,
+            config,
         }
     }
-}
 
-impl<'a> HistoricalMatchupModel<'a> {
     pub fn get_hits(&self) -> usize {
         self.dist.iter().sum()
     }
@@ -51,9 +173,102 @@ impl<'a> HistoricalMatchupModel<'a> {
     pub fn exp2idx(&self, exp: f64) -> usize {
         (exp * (self.dist.len() as f64 - 1.)).round() as usize
     }
+
+    /// The away team's meetings with the home team that count toward its
+    /// record, at either building depending on [`Venues`]
+    pub fn record(
+        &self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+        as_of: Option<&Game>,
+    ) -> rusqlite::Result<Record> {
+        let (away, home) = (away.into(), home.into());
+        if !self.config.from_games() {
+            let h2h = self.db.get_h2h(away, home)?;
+            return Ok(Record {
+                score: h2h.score,
+                games: h2h.total_games as f64,
+                meetings: h2h.total_games,
+            });
+        }
+        let reverse = match self.config.venues {
+            Venues::Split => None,
+            Venues::Combined => Some(0.),
+            // How much more the home teams score than the away teams
+            Venues::Adjusted => Some(1. - 2. * self.config.baseline),
+        };
+        self.tally(away, home, as_of, reverse)
+    }
+
+    /// The away team's meetings at the home team's building before `as_of`,
+    /// each weighing by its age. Without a game, every stored meeting up to
+    /// today counts.
+    pub fn history(
+        &self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+        as_of: Option<&Game>,
+    ) -> rusqlite::Result<Record> {
+        self.tally(away.into(), home.into(), as_of, None)
+    }
+
+    /// Like [`history`](Self::history), also counting the home team's visits
+    /// to the away team with `reverse` taken off their score when given. The
+    /// window of meetings is over both venues together.
+    fn tally(
+        &self,
+        away: TeamID,
+        home: TeamID,
+        as_of: Option<&Game>,
+        reverse: Option<f64>,
+    ) -> rusqlite::Result<Record> {
+        let (date, id, season) = match as_of {
+            Some(game) => (game.date, game.id, season_of(game)),
+            None => {
+                let today = Local::now().date_naive();
+                (today, i64::MAX, season_from_date(today))
+            }
+        };
+        // Seasons are numbered like 20232024, so the one before is 10001 less
+        let since = self.config.seasons.map_or(0, |seasons| {
+            season.saturating_sub(10001 * (seasons.max(1) - 1))
+        });
+        let limit = self.config.meetings;
+        let mut meetings = self
+            .db
+            .meetings(away, home, (date, id), since, limit)?
+            .into_iter()
+            .map(|meeting| (meeting, None))
+            .collect::<Vec<_>>();
+        if let Some(adjustment) = reverse {
+            let visits = self.db.meetings(home, away, (date, id), since, limit)?;
+            meetings.extend(visits.into_iter().map(|visit| (visit, Some(adjustment))));
+            meetings.sort_by_key(|(meeting, _)| Reverse((meeting.date, meeting.id)));
+            meetings.truncate(limit.unwrap_or(meetings.len()));
+        }
+        let mut record = Record {
+            meetings: meetings.len() as u32,
+            ..Default::default()
+        };
+        for (meeting, reverse) in &meetings {
+            let weight = self.config.half_life.map_or(1., |half_life| {
+                let age = (date - meeting.date).num_days() as f64 / YEAR;
+                0.5f64.powf(age / half_life)
+            });
+            let score = meeting.result(self.config.overtime).score;
+            // A visit's result is the other team's, turned around
+            let score = match reverse {
+                Some(adjustment) => 1. - adjustment - score,
+                None => score,
+            };
+            record.score += weight * score;
+            record.games += weight;
+        }
+        Ok(record)
+    }
 }
 
-impl<'a> Model<Head2Head> for HistoricalMatchupModel<'a> {
+impl<S: Storage> Model<Head2Head> for HistoricalMatchupModel<S> {
     fn predict(
         &self,
         away: impl Into<TeamID>,
@@ -66,12 +281,62 @@ impl<'a> Model<Head2Head> for HistoricalMatchupModel<'a> {
         &self,
         away: impl Into<TeamID>,
         home: impl Into<TeamID>,
+    ) -> rusqlite::Result<(Head2Head, Head2Head, Prediction)> {
+        self.predict_with_prior(away, home, self.config.baseline, None)
+    }
+
+    fn update(
+        &mut self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+        outcome: Outcomes,
+    ) -> rusqlite::Result<()> {
+        self.predict_and_update(away, home, outcome).map(|_| ())
+    }
+
+    fn predict_and_update_result(
+        &mut self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+        result: &GameResult,
+    ) -> rusqlite::Result<Prediction> {
+        self.predict_and_update_with_prior(away, home, result, self.config.baseline, None)
+    }
+}
+
+impl<S: Storage> HistoricalMatchupModel<S> {
+    /// What the record is shrunk toward given the predictions `earlier`
+    /// models made for the game, the league baseline when the ranker's isn't
+    /// among them
+    pub fn prior(&self, earlier: &[(&'static str, Prediction)]) -> f64 {
+        let prior = match self.config.prior {
+            HistoricalPrior::League => None,
+            HistoricalPrior::Ranking => earlier
+                .iter()
+                .find(|(label, _)| *label == ranker::LABEL)
+                .map(|(_, prediction)| away_prob(prediction)),
+        };
+        prior.unwrap_or(self.config.baseline)
+    }
+
+    /// Like [`Model::predict_and_get`], shrinking the record toward the away
+    /// win probability `prior`. Windowed records only look at the meetings
+    /// before `as_of`, the game being predicted.
+    pub fn predict_with_prior(
+        &self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+        prior: f64,
+        as_of: Option<&Game>,
     ) -> rusqlite::Result<(Head2Head, Head2Head, Prediction)> {
         let id1 = away.into();
         let id2 = home.into();
         let h2h_away = self.db.get_h2h(id1, id2)?;
         let h2h_home = self.db.get_h2h(id2, id1)?;
-        let (exp_away, exp_home) = (h2h_away.team_win_freq, h2h_home.team_win_freq);
+        let record = self.record(id1, id2, as_of)?;
+        debug!("{id1} @ {id2} over {} meetings", record.meetings);
+        let exp_away = self.config.estimate(record.score, record.games, prior);
+        let exp_home = 1. - exp_away;
         let outcome = outcome_from_prob(exp_away, exp_home);
         Ok((
             h2h_away,
@@ -84,30 +349,25 @@ impl<'a> Model<Head2Head> for HistoricalMatchupModel<'a> {
         ))
     }
 
-    fn update(
-        &mut self,
-        away: impl Into<TeamID>,
-        home: impl Into<TeamID>,
-        outcome: Outcomes,
-    ) -> rusqlite::Result<()> {
-        self.predict_and_update(away, home, outcome).map(|_| ())
-    }
-
-    fn predict_and_update(
+    /// Like [`Model::predict_and_update_result`], shrinking the record toward
+    /// the away win probability `prior` and windowing it as of `as_of`
+    pub fn predict_and_update_with_prior(
         &mut self,
         away: impl Into<TeamID>,
         home: impl Into<TeamID>,
-        outcome: Outcomes,
+        result: &GameResult,
+        prior: f64,
+        as_of: Option<&Game>,
     ) -> rusqlite::Result<Prediction> {
-        let (mut h2h_away, mut h2h_home, predic) = self.predict_and_get(away, home)?;
+        let (mut h2h_away, mut h2h_home, predic) =
+            self.predict_with_prior(away, home, prior, as_of)?;
+        let outcome = result.outcome;
         // debug!("{h2h_away:#?}");
+        h2h_away.update(result);
+        h2h_home.update(&result.flip());
         let winexp = if let Outcomes::WIN = outcome {
-            h2h_away.update(Outcomes::WIN);
-            h2h_home.update(Outcomes::LOSS);
             &predic.exp_away
         } else {
-            h2h_away.update(Outcomes::LOSS);
-            h2h_home.update(Outcomes::WIN);
             &predic.exp_home
         };
         // debug!("{h2h_away:#?}");
@@ -121,3 +381,221 @@ impl<'a> Model<Head2Head> for HistoricalMatchupModel<'a> {
         Ok(predic)
     }
 }
+
+/// Name of the head to head model in the metrics
+pub const LABEL: &str = "Head2Head";
+
+impl<S: Storage> GameModel for HistoricalMatchupModel<S> {
+    fn label(&self) -> &'static str {
+        LABEL
+    }
+
+    fn column(&self) -> &'static str {
+        "hist"
+    }
+
+    fn settings(&self) -> String {
+        format!("{:?}", self.config)
+    }
+
+    fn predict_game(
+        &self,
+        away: TeamID,
+        home: TeamID,
+        earlier: &[(&'static str, Prediction)],
+    ) -> rusqlite::Result<Prediction> {
+        self.predict_with_prior(away, home, self.prior(earlier), None)
+            .map(|(_, _, prediction)| prediction)
+    }
+
+    fn observe(
+        &mut self,
+        game: &Game,
+        result: &GameResult,
+        earlier: &[(&'static str, Prediction)],
+    ) -> rusqlite::Result<Prediction> {
+        let prior = self.prior(earlier);
+        self.predict_and_update_with_prior(game.away_id, game.home_id, result, prior, Some(game))
+    }
+
+    fn hits(&self) -> usize {
+        self.succ
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranking_prior_comes_from_the_ranker() -> anyhow::Result<()> {
+        let db = DataBase::new(":memory:")?;
+        let ranking = HistoricalMatchupModel::with_config(
+            &db,
+            HistoricalConfig {
+                prior: HistoricalPrior::Ranking,
+                baseline: 0.45,
+                ..Default::default()
+            },
+        );
+        let rank = || Prediction {
+            exp_away: 0.7,
+            exp_home: 0.3,
+            outcome: Outcomes::WIN,
+        };
+        assert!((ranking.prior(&[(ranker::LABEL, rank())]) - 0.7).abs() < 1e-12);
+        assert_eq!(ranking.prior(&[]), 0.45);
+        let league = HistoricalMatchupModel::from(&db);
+        assert_eq!(
+            league.prior(&[(ranker::LABEL, rank())]),
+            league.config.baseline
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn short_records_shrink_to_the_prior() -> anyhow::Result<()> {
+        let db = DataBase::new(":memory:")?;
+        for (id, abbrev) in [(8, "MTL"), (10, "TOR")] {
+            db.add_team(id, abbrev.to_string(), abbrev.to_string())?;
+        }
+        let (mtl, tor) = (db.get_team(8)?, db.get_team(10)?);
+        db.add_h2h(&mtl.vs(&tor))?;
+        db.add_h2h(&tor.vs(&mtl))?;
+        let mut model = HistoricalMatchupModel::from(&db);
+        assert_eq!(model.predict(8, 10)?.exp_away, 0.5);
+        model.update(8, 10, Outcomes::WIN)?;
+        let prediction = model.predict(8, 10)?;
+        assert!((prediction.exp_away - 6. / 11.).abs() < 1e-12);
+        assert!((prediction.exp_away + prediction.exp_home - 1.).abs() < 1e-12);
+        let (.., prediction) = model.predict_with_prior(8, 10, 0.3, None)?;
+        assert!((prediction.exp_away - 4. / 11.).abs() < 1e-12);
+        let raw = HistoricalMatchupModel::with_config(
+            &db,
+            HistoricalConfig {
+                prior_strength: 0.,
+                ..Default::default()
+            },
+        );
+        assert_eq!(raw.predict(8, 10)?.exp_away, 1.);
+        assert_eq!(raw.predict(10, 8)?.exp_away, 0.);
+        Ok(())
+    }
+
+    #[test]
+    fn windows_look_at_recent_meetings() -> anyhow::Result<()> {
+        use chrono::NaiveDate;
+        use nhl_api::{GameType, PeriodType};
+
+        let db = DataBase::new(":memory:")?;
+        // Montréal lost its first three visits and won the next two, a
+        // season apart each
+        for (i, score) in [(1, 4), (2, 3), (0, 1), (5, 2), (3, 1)]
+            .into_iter()
+            .enumerate()
+        {
+            let year = 2019 + i as i32;
+            db.add_game(&Game {
+                id: 2019020001 + i as i64,
+                date: NaiveDate::from_ymd_opt(year, 11, 1).unwrap(),
+                season: year as u32 * 10001 + 1,
+                game_type: GameType::RegularSeason,
+                away_id: 8,
+                home_id: 10,
+                score,
+                resolution: PeriodType::Regulation,
+            })?;
+        }
+        let next = Game {
+            id: 2024020001,
+            date: NaiveDate::from_ymd_opt(2024, 11, 1).unwrap(),
+            season: 20242025,
+            game_type: GameType::RegularSeason,
+            away_id: 8,
+            home_id: 10,
+            score: (0, 0),
+            resolution: PeriodType::Regulation,
+        };
+        let windowed = |config: HistoricalConfig| {
+            HistoricalMatchupModel::with_config(&db, config).record(8, 10, Some(&next))
+        };
+        let all = HistoricalConfig {
+            meetings: Some(100),
+            ..Default::default()
+        };
+        let record = windowed(all)?;
+        assert_eq!((record.score, record.games, record.meetings), (2., 5., 5));
+        let last_two = HistoricalConfig {
+            meetings: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(windowed(last_two)?.score, 2.);
+        let three_seasons = HistoricalConfig {
+            seasons: Some(3),
+            ..Default::default()
+        };
+        let record = windowed(three_seasons)?;
+        assert_eq!((record.score, record.meetings), (2., 2));
+        let decayed = HistoricalConfig {
+            half_life: Some(1.),
+            ..Default::default()
+        };
+        let record = windowed(decayed)?;
+        assert!(record.score / record.games > 0.5);
+        assert!(record.games < 2.);
+        assert_eq!(record.meetings, 5);
+        // Only the meetings before the game being predicted count
+        let earlier = HistoricalMatchupModel::with_config(&db, all).history(
+            8,
+            10,
+            Some(&Game {
+                id: 2019020003,
+                date: NaiveDate::from_ymd_opt(2021, 11, 1).unwrap(),
+                ..next
+            }),
+        )?;
+        assert_eq!((earlier.score, earlier.meetings), (0., 2));
+        // Toronto won its visit in between, which only counts with both venues
+        db.add_game(&Game {
+            id: 2023020500,
+            date: NaiveDate::from_ymd_opt(2023, 12, 1).unwrap(),
+            season: 20232024,
+            away_id: 10,
+            home_id: 8,
+            score: (4, 1),
+            ..next
+        })?;
+        let record = windowed(all)?;
+        assert_eq!((record.score, record.games, record.meetings), (2., 6., 6));
+        // The last meetings are counted over both venues together, even with
+        // fewer of them at Montréal than the window holds
+        let last = |meetings| {
+            windowed(HistoricalConfig {
+                meetings: Some(meetings),
+                ..Default::default()
+            })
+        };
+        let record = last(2)?;
+        assert_eq!((record.score, record.meetings), (1., 2));
+        let record = last(3)?;
+        assert_eq!((record.score, record.meetings), (2., 3));
+        let split = HistoricalConfig {
+            venues: Venues::Split,
+            ..Default::default()
+        };
+        assert_eq!(windowed(split)?.meetings, 5);
+        let adjusted = HistoricalConfig {
+            venues: Venues::Adjusted,
+            baseline: 0.45,
+            ..Default::default()
+        };
+        let record = windowed(adjusted)?;
+        assert!((record.score - 1.9).abs() < 1e-12);
+        assert_eq!(record.meetings, 6);
+        Ok(())
+    }
+}
@@ -6,30 +6,79 @@ use skillratings::Outcomes;
 use crate::{
     data::{
         self,
-        db::{DataBase, TeamID},
+        db::TeamID,
         models::{
             self,
-            last10::Last10,
+            games::{Game, GameResult},
+            last10::{HISTORY, Last10},
             prediction::{self, Prediction},
             probability::DiscreteProb,
         },
+        storage::Storage,
     },
-    model::model::{Model, ModelBase},
+    model::model::{GameModel, Model, ModelBase},
+    rating::overtime::OvertimePolicy,
 };
 
-pub type Last10GamesModel<'a> = ModelBase<'a, [usize; 11]>;
+/// How a team's recent games are turned into its expected score
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FormConfig {
+    /// Games looked back at, at most [`HISTORY`]
+    pub window: usize,
+    /// Weight of each game relative to the one after it, every game counting
+    /// the same when unset
+    pub decay: Option<f64>,
+    pub overtime: OvertimePolicy,
+}
 
-impl<'a> From<&'a DataBase> for Last10GamesModel<'a> {
-    fn from(db: &'a DataBase) -> Self {
+impl Default for FormConfig {
+    fn default() -> Self {
+        Self {
+            window: 10,
+            decay: None,
+            overtime: OvertimePolicy::default(),
+        }
+    }
+}
+
+impl FormConfig {
+    /// The team's score over the window, most recent games weighing most
+    /// with a decay. Games a young team hasn't played yet count as half a
+    /// win, so its estimate starts even and grows into its record.
+    pub fn estimate(&self, form: &Last10) -> f64 {
+        let window = self.window.clamp(1, HISTORY);
+        let mut scores = form
+            .recent(window)
+            .map(|&(outcome, overtime)| self.overtime.score(outcome, overtime));
+        let (mut total, mut weights, mut weight) = (0., 0., 1.);
+        for _ in 0..window {
+            total += weight * scores.next().unwrap_or(0.5);
+            weights += weight;
+            weight *= self.decay.unwrap_or(1.);
+        }
+        total / weights
+    }
+}
+
+/// Scores the teams' recent form
+pub type Last10GamesModel<S> = ModelBase<S, [usize; 11], FormConfig>;
+
+impl<S: Storage> From<S> for Last10GamesModel<S> {
+    fn from(db: S) -> Self {
+        Self::with_config(db, FormConfig::default())
+    }
+}
+
+impl<S: Storage> Last10GamesModel<S> {
+    pub fn with_config(db: S, config: FormConfig) -> Self {
         Self {
             db,
             dist: [0; 11],
             succ: 0,
+            config,
         }
     }
-}
 
-impl<'a> Last10GamesModel<'a> {
     pub fn get_hits(&self) -> usize {
         self.dist.iter().sum()
     }
@@ -55,7 +104,7 @@ impl<'a> Last10GamesModel<'a> {
     }
 }
 
-impl<'a> Model<Last10> for Last10GamesModel<'a> {
+impl<S: Storage> Model<Last10> for Last10GamesModel<S> {
     fn predict(
         &self,
         away: impl Into<TeamID>,
@@ -74,7 +123,7 @@ impl<'a> Model<Last10> for Last10GamesModel<'a> {
         let away10 = self.db.get_last10(id1)?;
         let home10 = self.db.get_last10(id2)?;
         // debug!("{away10:#?}");
-        let (exp_away, exp_home) = (away10.estimate(), home10.estimate());
+        let (exp_away, exp_home) = (self.config.estimate(&away10), self.config.estimate(&home10));
         let outcome = if exp_away > exp_home {
             Outcomes::WIN
         } else {
@@ -100,21 +149,22 @@ impl<'a> Model<Last10> for Last10GamesModel<'a> {
         self.predict_and_update(away, home, outcome).map(|_| ())
     }
 
-    fn predict_and_update(
+    fn predict_and_update_result(
         &mut self,
         away: impl Into<TeamID>,
         home: impl Into<TeamID>,
-        outcome: Outcomes,
+        result: &GameResult,
     ) -> rusqlite::Result<Prediction> {
         let (mut away10, mut home10, prediction) = self.predict_and_get(away, home)?;
-        let idx = if let Outcomes::WIN = outcome {
-            away10.wins
+        let outcome = result.outcome;
+        let idx = self.exp2idx(if let Outcomes::WIN = outcome {
+            prediction.exp_away
         } else {
-            home10.wins
-        } as usize;
+            prediction.exp_home
+        });
         self.dist[idx] += 1;
-        away10.update(outcome);
-        home10.update(outcome);
+        away10.update(result);
+        home10.update(&result.flip());
         self.db.update_last10(away10)?;
         self.db.update_last10(home10)?;
         if prediction.outcome == outcome {
@@ -123,3 +173,88 @@ impl<'a> Model<Last10> for Last10GamesModel<'a> {
         Ok(prediction)
     }
 }
+
+/// Name of the form model in the metrics
+pub const LABEL: &str = "Last 10 Games";
+
+impl<S: Storage> GameModel for Last10GamesModel<S> {
+    fn label(&self) -> &'static str {
+        LABEL
+    }
+
+    fn column(&self) -> &'static str {
+        "la10"
+    }
+
+    fn settings(&self) -> String {
+        format!("{:?}", self.config)
+    }
+
+    fn predict_game(
+        &self,
+        away: TeamID,
+        home: TeamID,
+        _earlier: &[(&'static str, Prediction)],
+    ) -> rusqlite::Result<Prediction> {
+        self.predict(away, home)
+    }
+
+    fn observe(
+        &mut self,
+        game: &Game,
+        result: &GameResult,
+        _earlier: &[(&'static str, Prediction)],
+    ) -> rusqlite::Result<Prediction> {
+        self.predict_and_update_result(game.away_id, game.home_id, result)
+    }
+
+    fn hits(&self) -> usize {
+        self.succ
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form(results: &[Outcomes]) -> Last10 {
+        let mut form = Last10 {
+            id: 8,
+            wins: 0,
+            loss: 0,
+            otl: 0,
+            games: Default::default(),
+            played: 0,
+        };
+        for &outcome in results {
+            form.update(&outcome.into());
+        }
+        form
+    }
+
+    #[test]
+    fn fills_short_histories_with_half_wins() {
+        let config = FormConfig::default();
+        assert_eq!(config.estimate(&form(&[])), 0.5);
+        assert_eq!(config.estimate(&form(&[Outcomes::WIN; 2])), 0.6);
+        let streak = form(&[[Outcomes::LOSS; 20], [Outcomes::WIN; 20]].concat());
+        assert_eq!((streak.wins, streak.loss, streak.played), (10, 0, 40));
+        assert_eq!(streak.games.len(), HISTORY);
+        assert_eq!(config.estimate(&streak), 1.);
+        let longer = FormConfig {
+            window: 30,
+            ..config
+        };
+        assert_eq!(longer.estimate(&streak), 2. / 3.);
+        let decayed = FormConfig {
+            window: 30,
+            decay: Some(0.5),
+            ..config
+        };
+        assert!(decayed.estimate(&streak) > 0.99);
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use skillratings::Outcomes;
+
+use crate::data::models::prediction::Prediction;
+
+/// Keeps the away side's probability away from 0 and 1 so a single confident
+/// miss can't make the log loss infinite
+const CLAMP: f64 = 1e-12;
+
+/// Games in the rolling window unless configured otherwise
+pub const WINDOW: usize = 500;
+
+/// The scores of a single prediction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Score {
+    pub hit: bool,
+    pub log_loss: f64,
+    pub brier: f64,
+    pub rps: f64,
+}
+
+impl Score {
+    /// Scores `prediction` against who actually won. The probabilities are
+    /// normalised first, and ties count as half a win for either side.
+    pub fn new(prediction: &Prediction, outcome: Outcomes) -> Self {
+        let actual = actual_score(outcome);
+        let exp = away_prob(prediction).clamp(CLAMP, 1. - CLAMP);
+        // Ranked over home win, tie, away win. The models never give a tie
+        // any weight, so this only parts from the Brier score on tied games.
+        let (home_won, tied) = match outcome {
+            Outcomes::WIN => (0., 0.),
+            Outcomes::DRAW => (0., 1.),
+            Outcomes::LOSS => (1., 0.),
+        };
+        let home = 1. - exp;
+        let rps = ((home - home_won).powi(2) + (home - home_won - tied).powi(2)) / 2.;
+        Self {
+            hit: prediction.outcome == outcome,
+            log_loss: -(actual * exp.ln() + (1. - actual) * (1. - exp).ln()),
+            brier: (exp - actual).powi(2),
+            rps,
+        }
+    }
+}
+
+/// The away side's score in a game, half a win for a tie
+pub fn actual_score(outcome: Outcomes) -> f64 {
+    match outcome {
+        Outcomes::WIN => 1.,
+        Outcomes::LOSS => 0.,
+        Outcomes::DRAW => 0.5,
+    }
+}
+
+/// The away side's share of a prediction's probabilities
+pub fn away_prob(prediction: &Prediction) -> f64 {
+    let total = prediction.exp_away + prediction.exp_home;
+    if total > 0. {
+        prediction.exp_away / total
+    } else {
+        0.5
+    }
+}
+
+/// How good a model's predictions were over a number of games
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Metrics {
+    pub games: usize,
+    /// Games whose winner was picked right
+    pub hits: usize,
+    log_loss: f64,
+    brier: f64,
+    rps: f64,
+}
+
+impl Metrics {
+    pub fn record(&mut self, prediction: &Prediction, outcome: Outcomes) {
+        self.add(&Score::new(prediction, outcome));
+    }
+
+    pub fn add(&mut self, score: &Score) {
+        self.games += 1;
+        self.hits += score.hit as usize;
+        self.log_loss += score.log_loss;
+        self.brier += score.brier;
+        self.rps += score.rps;
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        self.mean(self.hits as f64)
+    }
+
+    pub fn log_loss(&self) -> f64 {
+        self.mean(self.log_loss)
+    }
+
+    pub fn brier(&self) -> f64 {
+        self.mean(self.brier)
+    }
+
+    /// Ranked probability score
+    pub fn rps(&self) -> f64 {
+        self.mean(self.rps)
+    }
+
+    fn mean(&self, total: f64) -> f64 {
+        if self.games == 0 {
+            return f64::NAN;
+        }
+        total / self.games as f64
+    }
+}
+
+/// A model's [`Metrics`] over every game, by season and over the last few
+/// games
+#[derive(Debug, Clone)]
+pub struct Scores {
+    pub total: Metrics,
+    pub by_season: BTreeMap<u32, Metrics>,
+    window: VecDeque<Score>,
+    size: usize,
+}
+
+impl Default for Scores {
+    fn default() -> Self {
+        Self::new(WINDOW)
+    }
+}
+
+impl Scores {
+    /// Keeps a rolling window of the last `size` games
+    pub fn new(size: usize) -> Self {
+        Self {
+            total: Metrics::default(),
+            by_season: BTreeMap::new(),
+            window: VecDeque::with_capacity(size),
+            size,
+        }
+    }
+
+    pub fn record(&mut self, season: u32, prediction: &Prediction, outcome: Outcomes) {
+        let score = Score::new(prediction, outcome);
+        self.total.add(&score);
+        self.by_season.entry(season).or_default().add(&score);
+        if self.size == 0 {
+            return;
+        }
+        if self.window.len() == self.size {
+            self.window.pop_front();
+        }
+        self.window.push_back(score);
+    }
+
+    /// Games kept in the rolling window
+    pub fn window(&self) -> usize {
+        self.size
+    }
+
+    /// Metrics of the last games, up to the window size
+    pub fn rolling(&self) -> Metrics {
+        let mut metrics = Metrics::default();
+        self.window.iter().for_each(|score| metrics.add(score));
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_against_the_winner() {
+        let mut scores = Scores::new(1);
+        let prediction = Prediction {
+            exp_away: 0.8,
+            exp_home: 0.2,
+            outcome: Outcomes::WIN,
+        };
+        scores.record(20232024, &prediction, Outcomes::WIN);
+        scores.record(20242025, &prediction, Outcomes::LOSS);
+        let metrics = scores.total;
+        assert_eq!((metrics.games, metrics.hits), (2, 1));
+        assert_eq!(metrics.accuracy(), 0.5);
+        assert!((metrics.brier() - (0.04 + 0.64) / 2.).abs() < 1e-12);
+        assert!((metrics.rps() - metrics.brier()).abs() < 1e-12);
+        assert!((metrics.log_loss() + (0.8f64.ln() + 0.2f64.ln()) / 2.).abs() < 1e-12);
+        assert_eq!(scores.by_season[&20232024].hits, 1);
+        assert_eq!(scores.rolling().hits, 0);
+
+        let tie = Score::new(&prediction, Outcomes::DRAW);
+        assert!((tie.rps - (0.2f64.powi(2) + 0.8f64.powi(2)) / 2.).abs() < 1e-12);
+    }
+}
@@ -1,7 +1,10 @@
 use crate::{
     data::{
-        db::{DataBase, TeamID},
-        models::prediction::Prediction,
+        db::TeamID,
+        models::{
+            games::{Game, GameResult},
+            prediction::Prediction,
+        },
     },
     model::{historical::HistoricalMatchupModel, last10::Last10GamesModel, ranker::RankingModel},
 };
@@ -9,10 +12,19 @@ use nhl_api::Client;
 use skillratings::Outcomes;
 
 #[derive(Debug, Clone, Copy)]
-pub struct ModelBase<'a, T> {
-    pub db: &'a DataBase,
+pub struct ModelBase<S, T, C = ()> {
+    pub db: S,
     pub dist: T,
     pub succ: usize,
+    pub config: C,
+}
+
+impl<S, T: AsMut<[usize]>, C> ModelBase<S, T, C> {
+    /// Forgets the predictions made so far
+    pub fn clear(&mut self) {
+        self.dist.as_mut().fill(0);
+        self.succ = 0;
+    }
 }
 
 // impl<'a, T> From<&'a DataBase> for ModelBase<'a, T>
@@ -52,5 +64,66 @@ pub trait Model<T> {
         away: impl Into<TeamID>,
         home: impl Into<TeamID>,
         outcome: Outcomes,
+    ) -> rusqlite::Result<Prediction> {
+        self.predict_and_update_result(away, home, &outcome.into())
+    }
+
+    /// Like [`predict_and_update`](Self::predict_and_update), with the full
+    /// result so that overtime and the margin can be taken into account
+    fn predict_and_update_result(
+        &mut self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+        result: &GameResult,
+    ) -> rusqlite::Result<Prediction>;
+}
+
+/// The side of a model [`State`] drives, object safe so that it can hold any
+/// set of models chosen at runtime
+///
+/// [`State`]: crate::model::state::State
+pub trait GameModel {
+    /// Name its metrics are printed under
+    fn label(&self) -> &'static str;
+
+    /// Short name of its columns in the exported scores
+    fn column(&self) -> &'static str;
+
+    /// Name its calibrator is stored under
+    fn calibrator_key(&self) -> String {
+        self.label().to_string()
+    }
+
+    /// How the model is set up, stored next to its calibrator so that it is
+    /// only applied to the same model
+    fn settings(&self) -> String;
+
+    /// Predicts a game without learning from it, `earlier` holding what the
+    /// models ahead of this one predicted, as in [`observe`](Self::observe)
+    fn predict_game(
+        &self,
+        away: TeamID,
+        home: TeamID,
+        earlier: &[(&'static str, Prediction)],
+    ) -> rusqlite::Result<Prediction>;
+
+    /// Predicts `game` then learns from its `result`. `earlier` holds what
+    /// the models ahead of this one predicted for it, by label.
+    fn observe(
+        &mut self,
+        game: &Game,
+        result: &GameResult,
+        earlier: &[(&'static str, Prediction)],
     ) -> rusqlite::Result<Prediction>;
+
+    /// Runs when a new season starts, with the teams that played the last one
+    fn new_season(&mut self, _teams: &[TeamID]) -> rusqlite::Result<()> {
+        Ok(())
+    }
+
+    /// Games whose winner it picked
+    fn hits(&self) -> usize;
+
+    /// Forgets the games seen so far, the database being reset on its own
+    fn reset(&mut self);
 }
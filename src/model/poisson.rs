@@ -0,0 +1,315 @@
+use itertools::Itertools;
+use skillratings::Outcomes;
+
+use crate::{
+    data::{
+        db::{DataBase, TeamID},
+        models::{
+            games::{Game, GameResult},
+            prediction::Prediction,
+            probability::DiscreteProb,
+            strength::Strength,
+        },
+        storage::Storage,
+    },
+    model::model::{GameModel, Model, ModelBase},
+    utils::outcome_from_prob,
+};
+
+/// Goals counted on either side of a scoreline, the last one standing for
+/// that many or more
+pub const GOALS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoissonConfig {
+    /// Goals an average away team scores in regulation against an average
+    /// home team
+    pub away_rate: f64,
+    /// Goals an average home team scores in regulation
+    pub home_rate: f64,
+    /// How far each goal above or below the expected moves the strengths
+    pub learning_rate: f64,
+}
+
+impl Default for PoissonConfig {
+    fn default() -> Self {
+        Self {
+            away_rate: 2.8,
+            home_rate: 3.1,
+            learning_rate: 0.02,
+        }
+    }
+}
+
+/// Chances of every regulation scoreline between two teams whose goals are
+/// independent Poisson draws
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scoreline {
+    /// Expected goals of the away and home teams
+    pub rates: (f64, f64),
+    pub away: DiscreteProb<GOALS>,
+    pub home: DiscreteProb<GOALS>,
+    /// The away team leads after regulation
+    pub away_win: f64,
+    /// Regulation ends tied and the game goes to overtime
+    pub overtime: f64,
+    /// The home team leads after regulation
+    pub home_win: f64,
+}
+
+impl Scoreline {
+    pub fn new(away_rate: f64, home_rate: f64) -> Self {
+        let away = DiscreteProb::poisson(away_rate);
+        let home = DiscreteProb::poisson(home_rate);
+        let (mut away_win, mut overtime, mut home_win) = (0., 0., 0.);
+        for (i, pa) in away.pmf.iter().enumerate() {
+            for (j, ph) in home.pmf.iter().enumerate() {
+                match i.cmp(&j) {
+                    std::cmp::Ordering::Greater => away_win += pa * ph,
+                    std::cmp::Ordering::Equal => overtime += pa * ph,
+                    std::cmp::Ordering::Less => home_win += pa * ph,
+                }
+            }
+        }
+        Self {
+            rates: (away_rate, home_rate),
+            away,
+            home,
+            away_win,
+            overtime,
+            home_win,
+        }
+    }
+
+    /// Chance of the away team scoring `away` and the home team `home`
+    pub fn prob(&self, away: usize, home: usize) -> f64 {
+        self.away.pmf[away] * self.home.pmf[home]
+    }
+
+    /// The away team's chance of winning, overtime included. Past regulation
+    /// the next goal decides, so the away team takes its share of the
+    /// scoring.
+    pub fn away_prob(&self) -> f64 {
+        let (away, home) = self.rates;
+        self.away_win + self.overtime * away / (away + home)
+    }
+
+    /// The `n` likeliest scorelines, away goals first, likeliest first
+    pub fn most_likely(&self, n: usize) -> Vec<((usize, usize), f64)> {
+        (0..GOALS)
+            .cartesian_product(0..GOALS)
+            .map(|(away, home)| ((away, home), self.prob(away, home)))
+            .sorted_by(|(_, p1), (_, p2)| p2.total_cmp(p1))
+            .take(n)
+            .collect()
+    }
+}
+
+/// Predicts scorelines from the teams' attack and defence strengths, moved
+/// after every game toward the goals actually scored
+pub type PoissonModel<S> = ModelBase<S, [usize; 101], PoissonConfig>;
+
+impl<S: Storage> From<S> for PoissonModel<S> {
+    fn from(db: S) -> Self {
+        Self::with_config(db, PoissonConfig::default())
+    }
+}
+
+impl PoissonModel<&DataBase> {
+    /// Average regulation goals of the away and home teams over the stored
+    /// games, `None` without any
+    pub fn fit_rates(db: &DataBase) -> rusqlite::Result<Option<(f64, f64)>> {
+        let (ngames, away, home) = db.goal_rates()?;
+        Ok((ngames > 0).then_some((away, home)))
+    }
+}
+
+impl<S: Storage> PoissonModel<S> {
+    pub fn with_config(db: S, config: PoissonConfig) -> Self {
+        Self {
+            db,
+            dist: [0; 101],
+            succ: 0,
+            config,
+        }
+    }
+
+    /// Expected goals of either side given their strengths
+    fn rates(&self, away: &Strength, home: &Strength) -> (f64, f64) {
+        (
+            self.config.away_rate * (away.attack - home.defence).exp(),
+            self.config.home_rate * (home.attack - away.defence).exp(),
+        )
+    }
+
+    pub fn scoreline(
+        &self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+    ) -> rusqlite::Result<Scoreline> {
+        let away = self.db.get_strength(away.into())?;
+        let home = self.db.get_strength(home.into())?;
+        let (away_rate, home_rate) = self.rates(&away, &home);
+        Ok(Scoreline::new(away_rate, home_rate))
+    }
+
+    pub fn exp2idx(&self, exp: f64) -> usize {
+        (exp * (self.dist.len() as f64 - 1.)).round() as usize
+    }
+}
+
+impl<S: Storage> Model<Strength> for PoissonModel<S> {
+    fn predict(
+        &self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+    ) -> rusqlite::Result<Prediction> {
+        self.predict_and_get(away, home).map(|(_, _, pred)| pred)
+    }
+
+    fn predict_and_get(
+        &self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+    ) -> rusqlite::Result<(Strength, Strength, Prediction)> {
+        let away = self.db.get_strength(away.into())?;
+        let home = self.db.get_strength(home.into())?;
+        let (away_rate, home_rate) = self.rates(&away, &home);
+        let exp_away = Scoreline::new(away_rate, home_rate).away_prob();
+        let exp_home = 1. - exp_away;
+        let outcome = outcome_from_prob(exp_away, exp_home);
+        Ok((
+            away,
+            home,
+            Prediction {
+                exp_away,
+                exp_home,
+                outcome,
+            },
+        ))
+    }
+
+    fn update(
+        &mut self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+        outcome: Outcomes,
+    ) -> rusqlite::Result<()> {
+        self.predict_and_update(away, home, outcome).map(|_| ())
+    }
+
+    fn predict_and_update_result(
+        &mut self,
+        away: impl Into<TeamID>,
+        home: impl Into<TeamID>,
+        result: &GameResult,
+    ) -> rusqlite::Result<Prediction> {
+        let (mut away, mut home, prediction) = self.predict_and_get(away, home)?;
+        let outcome = result.outcome;
+        let idx = self.exp2idx(if let Outcomes::WIN = outcome {
+            prediction.exp_away
+        } else {
+            prediction.exp_home
+        });
+        self.dist[idx] += 1;
+        // A gradient step on the log likelihood of the regulation score
+        let (away_rate, home_rate) = self.rates(&away, &home);
+        let (away_goals, home_goals) = result.regulation_goals();
+        let rate = self.config.learning_rate;
+        let away_error = rate * (away_goals as f64 - away_rate);
+        let home_error = rate * (home_goals as f64 - home_rate);
+        away.attack += away_error;
+        home.defence -= away_error;
+        home.attack += home_error;
+        away.defence -= home_error;
+        self.db.update_strength(away)?;
+        self.db.update_strength(home)?;
+        if prediction.outcome == outcome {
+            self.succ += 1;
+        }
+        Ok(prediction)
+    }
+}
+
+/// Name of the Poisson model in the metrics
+pub const LABEL: &str = "Poisson";
+
+impl<S: Storage> GameModel for PoissonModel<S> {
+    fn label(&self) -> &'static str {
+        LABEL
+    }
+
+    fn column(&self) -> &'static str {
+        "pois"
+    }
+
+    fn settings(&self) -> String {
+        format!("{:?}", self.config)
+    }
+
+    fn predict_game(
+        &self,
+        away: TeamID,
+        home: TeamID,
+        _earlier: &[(&'static str, Prediction)],
+    ) -> rusqlite::Result<Prediction> {
+        self.predict(away, home)
+    }
+
+    fn observe(
+        &mut self,
+        game: &Game,
+        result: &GameResult,
+        _earlier: &[(&'static str, Prediction)],
+    ) -> rusqlite::Result<Prediction> {
+        self.predict_and_update_result(game.away_id, game.home_id, result)
+    }
+
+    fn hits(&self) -> usize {
+        self.succ
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scoring_teams_are_favoured() -> anyhow::Result<()> {
+        let scoreline = Scoreline::new(3., 3.);
+        let total = scoreline.away_win + scoreline.overtime + scoreline.home_win;
+        assert!((total - 1.).abs() < 1e-12);
+        assert!((scoreline.away_prob() - 0.5).abs() < 1e-12);
+        let likeliest = scoreline.most_likely(3);
+        assert_eq!(likeliest.len(), 3);
+        assert!(
+            likeliest
+                .iter()
+                .all(|&((away, home), _)| away.abs_diff(home) <= 1)
+        );
+        assert!((likeliest[0].1 - scoreline.prob(2, 2)).abs() < 1e-12);
+
+        let db = DataBase::new(":memory:")?;
+        let mut model = PoissonModel::from(&db);
+        let even = model.predict(8, 10)?;
+        assert!(even.exp_away < 0.5);
+        let mut result = GameResult::from(Outcomes::WIN);
+        result.goals = (6, 1);
+        for _ in 0..5 {
+            model.predict_and_update_result(8, 10, &result)?;
+        }
+        let strength = db.get_strength(8)?;
+        assert!(strength.attack > 0. && strength.defence > 0.);
+        assert_eq!(db.get_strength(10)?.attack, -strength.defence);
+        let prediction = model.predict(8, 10)?;
+        assert!(prediction.exp_away > 0.5);
+        assert_eq!(prediction.outcome, Outcomes::WIN);
+        let scoreline = model.scoreline(8, 10)?;
+        assert!(scoreline.rates.0 > scoreline.rates.1);
+        Ok(())
+    }
+}
@@ -1,15 +1,22 @@
-use skillratings::{
-    Outcomes,
-    weng_lin::{self, weng_lin, weng_lin_two_teams},
-};
+use skillratings::Outcomes;
 
 use crate::{
     data::{
         db::{DataBase, TeamID},
-        models::{prediction::Prediction, probability::DiscreteProb, teams::Team},
+        models::{
+            games::{Game, GameResult},
+            prediction::Prediction,
+            probability::DiscreteProb,
+        },
+        storage::Storage,
+    },
+    model::model::{GameModel, Model, ModelBase},
+    rating::{
+        home,
+        margin::MarginWeight,
+        season::SeasonTransform,
+        system::{Rating, RatingSystem, SystemConfig},
     },
-    model::model::{Model, ModelBase},
-    rating::openskill::RATING_CONFIG,
     utils::outcome_from_prob,
 };
 
@@ -20,19 +27,96 @@ use crate::{
 //     db: &'a DataBase,
 // }
 
-pub type RankingModel<'a> = ModelBase<'a, [usize; 10001]>;
+#[derive(Debug, Clone, Copy)]
+pub struct RankerConfig {
+    pub system: RatingSystem,
+    /// Tuning of the rating systems, like Weng-Lin's `beta` or Elo's K-factor
+    pub params: SystemConfig,
+    /// Applied to every active team when a new season starts
+    pub season: SeasonTransform,
+    /// Relocated and renamed teams start from their predecessor's rating and
+    /// head to heads
+    pub carry_over: bool,
+    /// Scales updates by the goal difference when set
+    pub margin: Option<MarginWeight>,
+    /// Added to the home team's rating whenever it plays, in the system's
+    /// units
+    pub home_advantage: f64,
+}
 
-impl<'a> From<&'a DataBase> for RankingModel<'a> {
-    fn from(db: &'a DataBase) -> Self {
+impl Default for RankerConfig {
+    fn default() -> Self {
+        Self {
+            system: RatingSystem::default(),
+            params: SystemConfig::default(),
+            season: SeasonTransform::default(),
+            carry_over: true,
+            margin: None,
+            home_advantage: 0.,
+        }
+    }
+}
+
+pub type RankingModel<S> = ModelBase<S, [usize; 10001], RankerConfig>;
+
+impl<S: Storage> From<S> for RankingModel<S> {
+    fn from(db: S) -> Self {
+        Self::with_config(db, RankerConfig::default())
+    }
+}
+
+impl RankingModel<&DataBase> {
+    /// Fits the home advantage to the stored games, `None` when there are none
+    pub fn fit_home_advantage(
+        db: &DataBase,
+        config: &RankerConfig,
+    ) -> rusqlite::Result<Option<f64>> {
+        let system = config.system;
+        let (ngames, home_score) = db.home_record()?;
+        if ngames == 0 {
+            return Ok(None);
+        }
+        let mut team = system.new_rating();
+        if let Some(uncertainty) = db.mean_uncertainty(system)? {
+            team = team.with_uncertainty(uncertainty);
+        }
+        Ok(Some(home::offset_for(home_score, team, &config.params)))
+    }
+}
+
+impl<S: Storage> RankingModel<S> {
+    pub fn with_config(db: S, config: RankerConfig) -> Self {
         Self {
             db,
             dist: [0; 10001],
             succ: 0,
+            config,
         }
     }
-}
 
-impl<'a> RankingModel<'a> {
+    /// Applies the off-season transform to `teams`, pulling them toward their
+    /// mean rating
+    pub fn new_season(&mut self, teams: &[TeamID]) -> rusqlite::Result<()> {
+        if teams.is_empty() {
+            return Ok(());
+        }
+        let system = self.config.system;
+        let ratings = teams
+            .iter()
+            .map(|&id| self.db.get_rating(id, system))
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mean = ratings.iter().map(Rating::mean).sum::<f64>() / ratings.len() as f64;
+        for (&id, rating) in teams.iter().zip(ratings) {
+            self.db
+                .update_rating(id, self.config.season.apply(rating, mean))?;
+        }
+        Ok(())
+    }
+
+    fn home_side(&self, rating: Rating) -> Rating {
+        rating.with_mean(rating.mean() + self.config.home_advantage)
+    }
+
     pub fn get_hits(&self) -> usize {
         self.dist.iter().sum()
     }
@@ -58,7 +142,7 @@ impl<'a> RankingModel<'a> {
     }
 }
 
-impl<'a> Model<Team> for RankingModel<'a> {
+impl<S: Storage> Model<Rating> for RankingModel<S> {
     fn predict(
         &self,
         away: impl Into<TeamID>,
@@ -71,17 +155,16 @@ impl<'a> Model<Team> for RankingModel<'a> {
         &self,
         away: impl Into<TeamID>,
         home: impl Into<TeamID>,
-    ) -> rusqlite::Result<(Team, Team, Prediction)> {
-        let id1 = away.into();
-        let id2 = home.into();
-        let team1 = self.db.get_team(id1)?;
-        let team2 = self.db.get_team(id2)?;
+    ) -> rusqlite::Result<(Rating, Rating, Prediction)> {
+        let system = self.config.system;
+        let away = self.db.get_rating(away.into(), system)?;
+        let home = self.db.get_rating(home.into(), system)?;
         let (exp_away, exp_home) =
-            weng_lin::expected_score(&team1.rating, &team2.rating, &RATING_CONFIG);
+            system.expected_score(&self.config.params, &away, &self.home_side(home));
         let outcome = outcome_from_prob(exp_away, exp_home);
         Ok((
-            team1,
-            team2,
+            away,
+            home,
             Prediction {
                 exp_away,
                 exp_home,
@@ -99,18 +182,34 @@ impl<'a> Model<Team> for RankingModel<'a> {
         self.predict_and_update(away, home, outcome).map(|_| ())
     }
 
-    fn predict_and_update(
+    /// Partial results move the ratings part of the way from a draw toward a
+    /// full win. With the margin of victory mode on the step is scaled by the
+    /// goal difference; only the ratings are scaled, the uncertainty shrinks
+    /// as after any other game. The home advantage is added for the update
+    /// and taken off again before the new rating is stored.
+    fn predict_and_update_result(
         &mut self,
         away: impl Into<TeamID>,
         home: impl Into<TeamID>,
-        outcome: Outcomes,
+        result: &GameResult,
     ) -> rusqlite::Result<Prediction> {
-        let (mut away, mut home, predic) = self.predict_and_get(away, home)?;
-        let (new_rank1, new_rank2) = weng_lin(&away.rating, &home.rating, &outcome, &RATING_CONFIG);
-        away.update(new_rank1);
-        home.update(new_rank2);
-        self.db.update_team_rating(away.id, new_rank1)?;
-        self.db.update_team_rating(home.id, new_rank2)?;
+        let (away_id, home_id) = (away.into(), home.into());
+        let (away, home, predic) = self.predict_and_get(away_id, home_id)?;
+        let system = self.config.system;
+        let outcome = result.outcome;
+        let home_rating = self.home_side(home);
+        let (mut new_rank1, mut new_rank2) =
+            system.rate(&self.config.params, &away, &home_rating, &outcome);
+        let share = (2. * result.score - 1.).abs();
+        if share < 1. {
+            let (draw1, draw2) =
+                system.rate(&self.config.params, &away, &home_rating, &Outcomes::DRAW);
+            new_rank1 =
+                new_rank1.with_mean(draw1.mean() + share * (new_rank1.mean() - draw1.mean()));
+            new_rank2 =
+                new_rank2.with_mean(draw2.mean() + share * (new_rank2.mean() - draw2.mean()));
+        }
+        new_rank2 = new_rank2.with_mean(new_rank2.mean() - self.config.home_advantage);
         let Prediction {
             exp_away, exp_home, ..
         } = &predic;
@@ -119,6 +218,15 @@ impl<'a> Model<Team> for RankingModel<'a> {
         } else {
             exp_home
         };
+        if let Some(weight) = self.config.margin {
+            let weight = weight.weight(result.margin, *winexp);
+            new_rank1 =
+                new_rank1.with_mean(away.mean() + weight * (new_rank1.mean() - away.mean()));
+            new_rank2 =
+                new_rank2.with_mean(home.mean() + weight * (new_rank2.mean() - home.mean()));
+        }
+        self.db.update_rating(away_id, new_rank1)?;
+        self.db.update_rating(home_id, new_rank2)?;
         if predic.outcome == outcome {
             self.succ += 1;
         }
@@ -127,3 +235,60 @@ impl<'a> Model<Team> for RankingModel<'a> {
         Ok(predic)
     }
 }
+
+/// Name of the ranker in the metrics
+pub const LABEL: &str = "ranking";
+
+impl<S: Storage> GameModel for RankingModel<S> {
+    fn label(&self) -> &'static str {
+        LABEL
+    }
+
+    fn column(&self) -> &'static str {
+        "rank"
+    }
+
+    fn calibrator_key(&self) -> String {
+        ranking_key(self.config.system)
+    }
+
+    fn settings(&self) -> String {
+        format!("{:?}", self.config)
+    }
+
+    fn predict_game(
+        &self,
+        away: TeamID,
+        home: TeamID,
+        _earlier: &[(&'static str, Prediction)],
+    ) -> rusqlite::Result<Prediction> {
+        self.predict(away, home)
+    }
+
+    fn observe(
+        &mut self,
+        game: &Game,
+        result: &GameResult,
+        _earlier: &[(&'static str, Prediction)],
+    ) -> rusqlite::Result<Prediction> {
+        self.predict_and_update_result(game.away_id, game.home_id, result)
+    }
+
+    fn new_season(&mut self, teams: &[TeamID]) -> rusqlite::Result<()> {
+        RankingModel::new_season(self, teams)
+    }
+
+    fn hits(&self) -> usize {
+        self.succ
+    }
+
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+/// Name the ranker's calibrator is stored under, which depends on the rating
+/// system it was fitted for
+pub fn ranking_key(system: RatingSystem) -> String {
+    format!("{LABEL}/{}", system.name())
+}
@@ -0,0 +1,70 @@
+use clap::ValueEnum;
+
+use crate::{
+    data::storage::Storage,
+    model::{
+        goals::GoalFormModel,
+        historical::{HistoricalConfig, HistoricalMatchupModel},
+        last10::{FormConfig, Last10GamesModel},
+        model::GameModel,
+        poisson::PoissonModel,
+        ranker::RankingModel,
+        state::StateConfig,
+    },
+};
+
+/// The models [`State`] knows how to build, to pick from at runtime
+///
+/// [`State`]: crate::model::state::State
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum ModelKind {
+    /// Rating system picked with `--system`
+    Ranking,
+    /// Head to head records
+    #[value(name = "h2h")]
+    Head2Head,
+    /// Recent results
+    Form,
+    /// Pythagorean expectation of the recent goals
+    GoalForm,
+    /// Scorelines from attack and defence strengths
+    Poisson,
+}
+
+impl ModelKind {
+    /// Every model, in the order they run by default
+    pub const ALL: [ModelKind; 5] = [
+        ModelKind::Ranking,
+        ModelKind::Head2Head,
+        ModelKind::Form,
+        ModelKind::GoalForm,
+        ModelKind::Poisson,
+    ];
+
+    /// The model set up with its part of `config` on top of `db`, scoring
+    /// games past regulation with `config.overtime`
+    pub fn build<'a, S>(self, db: S, config: &StateConfig) -> Box<dyn GameModel + Send + 'a>
+    where
+        S: Storage + Send + 'a,
+    {
+        match self {
+            ModelKind::Ranking => Box::new(RankingModel::with_config(db, config.ranker)),
+            ModelKind::Head2Head => Box::new(HistoricalMatchupModel::with_config(
+                db,
+                HistoricalConfig {
+                    overtime: config.overtime,
+                    ..config.hist
+                },
+            )),
+            ModelKind::Form => Box::new(Last10GamesModel::with_config(
+                db,
+                FormConfig {
+                    overtime: config.overtime,
+                    ..config.form
+                },
+            )),
+            ModelKind::GoalForm => Box::new(GoalFormModel::with_config(db, config.goals)),
+            ModelKind::Poisson => Box::new(PoissonModel::with_config(db, config.poisson)),
+        }
+    }
+}
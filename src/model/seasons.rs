@@ -0,0 +1,129 @@
+use std::collections::BTreeSet;
+
+use chrono::NaiveDate;
+
+use crate::{
+    data::{db::TeamID, models::games::Game, storage::GameStore},
+    utils::{season_from_date, season_of},
+};
+
+/// Follows the season the games belong to, running the off-season transform
+/// and the franchise carry-overs whenever a new one starts
+#[derive(Debug, Clone, Default)]
+pub struct Seasons {
+    /// Season of the last game seen, `None` until it is looked up
+    current: Option<u32>,
+    /// Teams that played in `current`
+    active: BTreeSet<TeamID>,
+    /// Day of the first game of `current`, `None` when resumed
+    started: Option<NaiveDate>,
+    resumed: bool,
+}
+
+impl Seasons {
+    pub fn current(&self) -> Option<u32> {
+        self.current
+    }
+
+    /// Calls `new_season` with the teams of the last season when `game` is the
+    /// first of a new one, then carries relocated and renamed teams over if
+    /// `carry_over`. A succession carries over into the first season entered
+    /// on or after its date, even when seasons without games lie between.
+    /// The season a previous run stopped in is recovered from the store.
+    pub fn enter(
+        &mut self,
+        db: &impl GameStore,
+        game: &Game,
+        carry_over: bool,
+        mut new_season: impl FnMut(&[TeamID]) -> rusqlite::Result<()>,
+    ) -> rusqlite::Result<()> {
+        if !self.resumed {
+            self.resumed = true;
+            if let Some(last) = db.last_processed_season()? {
+                self.current = Some(last);
+                self.active = db.season_teams(last)?.into_iter().collect();
+            }
+        }
+        let season = season_of(game);
+        match self.current {
+            Some(current) if current != season => {
+                let teams = self.active.iter().copied().collect::<Vec<_>>();
+                new_season(&teams)?;
+                self.active.clear();
+                if carry_over {
+                    for (from, to, date) in db.successions()? {
+                        // Those up to the start of the last season were
+                        // carried over then
+                        let pending = match self.started {
+                            Some(started) => started < date,
+                            None => season_from_date(date) >= current,
+                        };
+                        if pending && date <= game.date {
+                            db.carry_over(from, to)?;
+                        }
+                    }
+                }
+                self.started = Some(game.date);
+            }
+            Some(_) => {}
+            None => self.started = Some(game.date),
+        }
+        self.current = Some(season);
+        self.active.insert(game.away_id);
+        self.active.insert(game.home_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nhl_api::{GameType, PeriodType};
+
+    use super::*;
+    use crate::{
+        data::storage::{MemoryStore, Storage},
+        rating::system::RatingSystem,
+    };
+
+    fn game(id: i64, date: NaiveDate, away_id: TeamID, home_id: TeamID) -> Game {
+        Game {
+            id,
+            date,
+            season: season_from_date(date),
+            game_type: GameType::RegularSeason,
+            away_id,
+            home_id,
+            score: (3, 2),
+            resolution: PeriodType::Regulation,
+        }
+    }
+
+    #[test]
+    fn successions_carry_over_into_the_next_season_played() -> rusqlite::Result<()> {
+        let store = MemoryStore::new();
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let system = RatingSystem::Elo;
+        let rated = system.new_rating().with_mean(1600.);
+        store.update_rating(1, rated)?;
+        store.update_rating(3, rated)?;
+        // A move the summer before a season lost to a lockout, and a rename
+        // in September ahead of the season after it
+        store.add_succession(1, 2, date(2004, 7, 1));
+        store.add_succession(3, 4, date(2005, 9, 15));
+
+        let mut seasons = Seasons::default();
+        let mut enter = |game| seasons.enter(&store, &game, true, |_| Ok(()));
+        enter(game(1, date(2004, 3, 1), 1, 3))?;
+        assert_eq!(store.get_rating(2, system)?, system.new_rating());
+        enter(game(2, date(2005, 10, 5), 2, 4))?;
+        assert_eq!(store.get_rating(2, system)?, rated);
+        assert_eq!(store.get_rating(4, system)?, rated);
+        // Neither is carried over a second time
+        store.update_rating(2, system.new_rating())?;
+        store.update_rating(4, system.new_rating())?;
+        enter(game(3, date(2006, 10, 5), 2, 4))?;
+        assert_eq!(store.get_rating(2, system)?, system.new_rating());
+        assert_eq!(store.get_rating(4, system)?, system.new_rating());
+        Ok(())
+    }
+}
@@ -1,91 +1,662 @@
+use itertools::Itertools;
 use skillratings::Outcomes;
 
 use crate::{
     data::{
-        db::DataBase,
         models::{games::Game, prediction::Prediction},
+        storage::GameStore,
     },
     model::{
-        historical::HistoricalMatchupModel, last10::Last10GamesModel, model::Model,
-        ranker::RankingModel,
+        calibration::Calibration,
+        goals::GoalFormConfig,
+        historical::HistoricalConfig,
+        last10::FormConfig,
+        metrics::{self, Scores, away_prob},
+        model::GameModel,
+        poisson::PoissonConfig,
+        ranker::RankerConfig,
+        registry::ModelKind,
+        seasons::Seasons,
     },
+    rating::overtime::OvertimePolicy,
+    utils::{outcome_from_prob, season_of},
 };
 
-pub struct State<'a> {
-    db: &'a DataBase,
-    ranker: RankingModel<'a>,
-    hist: HistoricalMatchupModel<'a>,
-    last10: Last10GamesModel<'a>,
-    pub dist: Vec<Vec<Vec<usize>>>,
-    pub ngames: usize,
+/// Label of the ensemble, which averages the other models and comes after
+/// them in [`State::get_metrics`]
+pub const ENSEMBLE: &str = "ensemble";
+
+#[derive(Debug, Clone)]
+pub struct StateConfig {
+    /// Models to run, in order
+    pub models: Vec<ModelKind>,
+    pub ranker: RankerConfig,
+    /// Head to head records, meetings read back from the stored games being
+    /// scored with `overtime`
+    pub hist: HistoricalConfig,
+    /// How the models score games decided past regulation
+    pub overtime: OvertimePolicy,
+    /// Recent form of the teams, scored with `overtime` whatever its own
+    /// policy says
+    pub form: FormConfig,
+    pub goals: GoalFormConfig,
+    pub poisson: PoissonConfig,
+    /// Games in the rolling window of the metrics
+    pub window: usize,
 }
 
-impl<'a> From<&'a DataBase> for State<'a> {
-    fn from(db: &'a DataBase) -> Self {
+impl Default for StateConfig {
+    fn default() -> Self {
         Self {
-            db: db,
-            ranker: RankingModel::from(db),
-            hist: HistoricalMatchupModel::from(db),
-            last10: Last10GamesModel::from(db),
-            dist: vec![vec![vec![0; 11]; 1001]; 10001],
-            ngames: 0,
+            models: ModelKind::ALL.to_vec(),
+            ranker: RankerConfig::default(),
+            hist: HistoricalConfig::default(),
+            overtime: OvertimePolicy::default(),
+            form: FormConfig::default(),
+            goals: GoalFormConfig::default(),
+            poisson: PoissonConfig::default(),
+            window: metrics::WINDOW,
         }
     }
 }
 
-impl<'a> State<'a> {
-    pub fn process_game(&mut self, game: &Game) -> rusqlite::Result<[Prediction; 3]> {
-        self.ngames += 1;
-        let (away, home) = game.ids();
-        let (away_score, home_score) = game.score;
-        let outcome = if away_score > home_score {
-            Outcomes::WIN
-        } else {
-            Outcomes::LOSS
-        };
-        let pred1 = self.ranker.predict_and_update(away, home, outcome)?;
-        let pred2 = self.hist.predict_and_update(away, home, outcome)?;
-        let pred3 = self.last10.predict_and_update(away, home, outcome)?;
-        let idxr = if pred1.exp_away > pred1.exp_home {
-            self.ranker.exp2idx(pred1.exp_away)
-        } else {
-            self.ranker.exp2idx(pred1.exp_home)
-        };
-        let idxh = if pred2.exp_away > pred1.exp_home {
-            self.hist.exp2idx(pred1.exp_away)
-        } else {
-            self.hist.exp2idx(pred1.exp_home)
+/// Runs the models over the games of `S`, a [`DataBase`] or a
+/// [`MemoryStore`], scoring every prediction before the game is applied
+///
+/// [`DataBase`]: crate::data::db::DataBase
+/// [`MemoryStore`]: crate::data::storage::MemoryStore
+pub struct State<'a, S> {
+    db: S,
+    models: Vec<Box<dyn GameModel + Send + 'a>>,
+    pub ngames: usize,
+    seasons: Seasons,
+    overtime: OvertimePolicy,
+    /// Relocated and renamed teams start from their predecessor's records
+    carry_over: bool,
+    window: usize,
+    /// Per model, the ensemble averaging the others last
+    scores: Vec<Scores>,
+    /// Every game's away win probability and outcome per model, for fitting
+    /// calibrators
+    samples: Vec<Vec<(f64, Outcomes)>>,
+}
+
+impl<'a, S: GameStore + Clone + Send + 'a> From<S> for State<'a, S> {
+    fn from(db: S) -> Self {
+        Self::with_config(db, StateConfig::default())
+    }
+}
+
+impl<'a, S: GameStore + Clone + Send + 'a> State<'a, S> {
+    pub fn with_config(db: S, config: StateConfig) -> Self {
+        let mut state = Self {
+            db: db.clone(),
+            models: vec![],
+            ngames: 0,
+            seasons: Seasons::default(),
+            overtime: config.overtime,
+            carry_over: config.ranker.carry_over,
+            window: config.window,
+            scores: vec![Scores::new(config.window)],
+            samples: vec![vec![]],
         };
-        let idx10 = if pred3.exp_away > pred1.exp_home {
-            self.last10.exp2idx(pred1.exp_away)
+        for kind in config.models.iter().copied().unique() {
+            state.register(kind.build(db.clone(), &config));
+        }
+        state
+    }
+
+    /// Adds a model after the others, to be fed every game from now on
+    pub fn register(&mut self, model: Box<dyn GameModel + Send + 'a>) {
+        self.models.push(model);
+        // The ensemble stays last
+        let at = self.scores.len() - 1;
+        self.scores.insert(at, Scores::new(self.window));
+        self.samples.insert(at, vec![]);
+    }
+
+    /// Predicts and applies a game, recording it as processed. All of its
+    /// writes land in a single transaction, or join the batch already open.
+    pub fn process_game(&mut self, game: &Game) -> rusqlite::Result<Vec<Prediction>> {
+        let db = self.db.clone();
+        db.transaction(|| {
+            let predictions = self.apply(game)?;
+            db.mark_processed(game.id)?;
+            Ok(predictions)
+        })
+    }
+
+    fn apply(&mut self, game: &Game) -> rusqlite::Result<Vec<Prediction>> {
+        let models = &mut self.models;
+        self.seasons
+            .enter(&self.db, game, self.carry_over, |teams| {
+                models
+                    .iter_mut()
+                    .try_for_each(|model| model.new_season(teams))
+            })?;
+        self.ngames += 1;
+        let result = game.result(self.overtime);
+        let mut predictions: Vec<(&'static str, Prediction)> = vec![];
+        for model in &mut self.models {
+            let prediction = model.observe(game, &result, &predictions)?;
+            predictions.push((model.label(), prediction));
+        }
+        let mut predictions = predictions
+            .into_iter()
+            .map(|(_, prediction)| prediction)
+            .collect::<Vec<_>>();
+        let exp_away = if predictions.is_empty() {
+            0.5
         } else {
-            self.last10.exp2idx(pred1.exp_home)
+            predictions.iter().map(away_prob).sum::<f64>() / predictions.len() as f64
         };
-        self.dist[idxr][idxh][idx10] += 1;
-        Ok([pred1, pred2, pred3])
+        predictions.push(Prediction {
+            exp_away,
+            exp_home: 1. - exp_away,
+            outcome: outcome_from_prob(exp_away, 1. - exp_away),
+        });
+        let season = season_of(game);
+        for ((scores, samples), prediction) in self
+            .scores
+            .iter_mut()
+            .zip(&mut self.samples)
+            .zip(&predictions)
+        {
+            scores.record(season, prediction, result.outcome);
+            samples.push((away_prob(prediction), result.outcome));
+        }
+        // The ensemble is only kept for the metrics
+        predictions.pop();
+        Ok(predictions)
     }
 
+    /// Processes the games committing once every `batch_size` games
     pub fn process_games<'b>(
         &mut self,
         games: impl Iterator<Item = &'b Game>,
+        batch_size: usize,
     ) -> rusqlite::Result<()> {
-        for game in games {
-            let _ = self.process_game(game)?;
+        let db = self.db.clone();
+        for batch in &games.chunks(batch_size.max(1)) {
+            db.transaction(|| -> rusqlite::Result<()> {
+                for game in batch {
+                    let _ = self.process_game(game)?;
+                }
+                Ok(())
+            })?;
         }
         Ok(())
     }
 
+    /// Resets the models and feeds every stored game back through them in the
+    /// order they were played, without touching the network
+    pub fn replay(&mut self, batch_size: usize) -> rusqlite::Result<usize> {
+        self.db.reset_state()?;
+        self.models.iter_mut().for_each(|model| model.reset());
+        self.ngames = 0;
+        self.seasons = Seasons::default();
+        for scores in &mut self.scores {
+            *scores = Scores::new(scores.window());
+        }
+        self.samples.iter_mut().for_each(Vec::clear);
+        let games = self.db.get_games()?;
+        self.process_games(games.iter(), batch_size)?;
+        Ok(games.len())
+    }
+}
+
+impl<S> State<'_, S> {
+    /// Labels of the models in the order of [`get_metrics`], the ensemble
+    /// last
+    ///
+    /// [`get_metrics`]: Self::get_metrics
+    pub fn labels(&self) -> Vec<&'static str> {
+        self.models
+            .iter()
+            .map(|model| model.label())
+            .chain([ENSEMBLE])
+            .collect()
+    }
+
+    /// Short names of the models' columns in the exported scores, in the
+    /// order of their predictions
+    pub fn columns(&self) -> Vec<&'static str> {
+        self.models.iter().map(|model| model.column()).collect()
+    }
+
+    /// Games each model picked the winner of, out of all games, and its
+    /// accuracy
     pub fn get_accuracy(&self) -> Vec<(usize, usize, f64)> {
-        let mut acc = vec![];
-        let h1 = self.ranker.succ;
-        let acc1 = h1 as f64 / self.ngames as f64;
-        acc.push((h1, self.ngames, acc1));
-        let h2 = self.hist.succ;
-        let acc2 = h2 as f64 / self.ngames as f64;
-        acc.push((h2, self.ngames, acc2));
-        let h3 = self.last10.succ;
-        let acc3 = h3 as f64 / self.ngames as f64;
-        acc.push((h3, self.ngames, acc3));
-        acc
+        self.models
+            .iter()
+            .map(|model| {
+                let hits = model.hits();
+                (hits, self.ngames, hits as f64 / self.ngames as f64)
+            })
+            .collect()
+    }
+
+    /// Calibration of every model but the ensemble
+    pub fn get_calibration(&self, nbins: usize) -> Vec<Calibration> {
+        self.samples[..self.models.len()]
+            .iter()
+            .map(|samples| Calibration::from_samples(samples, nbins))
+            .collect()
+    }
+
+    /// The away win probability and outcome of every game processed, per
+    /// model in the order of [`labels`](Self::labels)
+    pub fn get_samples(&self) -> &[Vec<(f64, Outcomes)>] {
+        &self.samples
+    }
+
+    /// Names the models' calibrators are stored under, the ensemble last
+    pub fn calibrator_keys(&self) -> Vec<String> {
+        self.models
+            .iter()
+            .map(|model| model.calibrator_key())
+            .chain([ENSEMBLE.to_string()])
+            .collect()
+    }
+
+    /// How the models are set up, in the order of
+    /// [`calibrator_keys`](Self::calibrator_keys). The ensemble's is the
+    /// settings of all the others.
+    pub fn calibrator_settings(&self) -> Vec<String> {
+        let settings = self
+            .models
+            .iter()
+            .map(|model| model.settings())
+            .collect::<Vec<_>>();
+        let ensemble = self
+            .models
+            .iter()
+            .zip(&settings)
+            .map(|(model, settings)| format!("{}: {settings}", model.label()))
+            .join("; ");
+        settings.into_iter().chain([ensemble]).collect()
+    }
+
+    /// Log loss, Brier score and RPS of each model next to its accuracy, in
+    /// the order of [`labels`](Self::labels)
+    pub fn get_metrics(&self) -> &[Scores] {
+        &self.scores
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::NaiveDate;
+    use nhl_api::{GameType, PeriodType};
+
+    use super::*;
+    use crate::{
+        data::{
+            db::DataBase,
+            models::franchises::known_eras,
+            storage::{MemoryStore, Storage},
+        },
+        model::{
+            calibrator::{CalibrationMethod, Calibrator},
+            historical::{self, HistoricalPrior},
+        },
+        rating::{season::SeasonTransform, system::RatingSystem},
+        utils::season_from_date,
+    };
+
+    fn seeded() -> anyhow::Result<DataBase> {
+        let db = DataBase::new(":memory:")?;
+        for (id, name, abbrev) in [
+            (8, "Montréal Canadiens", "MTL"),
+            (10, "Toronto Maple Leafs", "TOR"),
+        ] {
+            db.add_team(id, name.to_string(), abbrev.to_string())?;
+            db.add_last10(id)?;
+        }
+        let (mtl, tor) = (db.get_team(8)?, db.get_team(10)?);
+        db.add_h2h(&mtl.vs(&tor))?;
+        db.add_h2h(&tor.vs(&mtl))?;
+        for (i, score) in [(4, 1), (2, 3), (5, 2)].into_iter().enumerate() {
+            db.add_game(&Game {
+                id: 2023020001 + i as i64,
+                date: NaiveDate::from_ymd_opt(2023, 10, 10 + i as u32).unwrap(),
+                season: 20232024,
+                game_type: GameType::RegularSeason,
+                away_id: 8,
+                home_id: 10,
+                score,
+                resolution: PeriodType::Regulation,
+            })?;
+        }
+        Ok(db)
+    }
+
+    #[test]
+    fn replay_is_repeatable() -> anyhow::Result<()> {
+        let db = seeded()?;
+        let mut state = State::from(&db);
+        assert_eq!(state.replay(2)?, 3);
+        let first = db.get_team(8)?.rating;
+        assert!(first.rating > 25.0);
+        assert_eq!(db.get_h2h(8, 10)?.team_wins, 2);
+
+        assert_eq!(state.replay(2)?, 3);
+        assert_eq!(db.get_team(8)?.rating, first);
+        assert_eq!(db.get_h2h(8, 10)?.total_games, 3);
+        assert_eq!(state.get_accuracy()[0].1, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn scores_every_model_and_the_ensemble() -> anyhow::Result<()> {
+        let db = seeded()?;
+        let mut state = State::from(&db);
+        state.replay(10)?;
+        let metrics = state.get_metrics();
+        assert_eq!(metrics.len(), ModelKind::ALL.len() + 1);
+        assert_eq!(metrics.last().unwrap().total.games, 3);
+        assert_eq!(metrics[0].total.hits, state.get_accuracy()[0].0);
+        assert_eq!(metrics[1].by_season[&20232024].games, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn reports_calibration_per_model() -> anyhow::Result<()> {
+        let db = seeded()?;
+        let mut state = State::from(&db);
+        state.replay(10)?;
+        let calibration = state.get_calibration(10);
+        assert_eq!(calibration.len(), ModelKind::ALL.len());
+        assert_eq!(calibration[0].games, 3);
+        assert!((0. ..=0.5).contains(&calibration[0].ece));
+        Ok(())
+    }
+
+    #[test]
+    fn stores_calibrators_by_model_and_settings() -> anyhow::Result<()> {
+        let db = seeded()?;
+        let mut state = State::from(&db);
+        state.replay(10)?;
+        let samples = &state.get_samples()[0];
+        assert_eq!(samples.len(), 3);
+        let pairs = samples
+            .iter()
+            .map(|&(exp, outcome)| (exp, metrics::actual_score(outcome)))
+            .collect::<Vec<_>>();
+        let calibrator = Calibrator::fit(CalibrationMethod::Isotonic, &pairs);
+        let key = &state.calibrator_keys()[0];
+        assert_eq!(key, "ranking/weng_lin");
+        let settings = &state.calibrator_settings()[0];
+        assert!(settings.starts_with("RankerConfig"));
+        db.set_calibrator(key, &calibrator, pairs.len(), settings)?;
+        assert_eq!(
+            db.get_calibrator(key)?,
+            Some((calibrator, settings.clone()))
+        );
+        assert_eq!(db.get_calibrator(historical::LABEL)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn replay_keeps_the_recent_scores() -> anyhow::Result<()> {
+        let db = seeded()?;
+        State::from(&db).replay(10)?;
+        assert_eq!(db.get_goal_form(8)?.games, [(4, 1), (2, 3), (5, 2)]);
+        Ok(())
+    }
+
+    #[test]
+    fn replay_moves_the_strengths() -> anyhow::Result<()> {
+        let db = seeded()?;
+        State::from(&db).replay(10)?;
+        let strength = db.get_strength(8)?;
+        assert!(strength.attack > 0. && strength.defence > 0.);
+        Ok(())
+    }
+
+    #[test]
+    fn runs_the_models_picked() -> anyhow::Result<()> {
+        let db = seeded()?;
+        let mut state = State::with_config(
+            &db,
+            StateConfig {
+                models: vec![ModelKind::Head2Head, ModelKind::Form, ModelKind::Form],
+                hist: HistoricalConfig {
+                    prior: HistoricalPrior::Ranking,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        assert_eq!(state.labels(), ["Head2Head", "Last 10 Games", ENSEMBLE]);
+        assert_eq!(state.columns(), ["hist", "la10"]);
+        let games = db.get_games()?;
+        // Without a ranker ahead of it, the head to head falls back on the
+        // league
+        let predictions = state.process_game(&games[0])?;
+        assert_eq!(predictions.len(), 2);
+        assert_eq!(predictions[0].exp_away, 0.5);
+        state.register(ModelKind::Ranking.build(&db, &StateConfig::default()));
+        assert_eq!(state.replay(10)?, 3);
+        assert_eq!(state.get_metrics().len(), 4);
+        assert_eq!(state.get_accuracy().len(), 3);
+        assert_eq!(state.calibrator_keys()[2], "ranking/weng_lin");
+        assert_eq!(state.get_samples()[3].len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn regresses_between_seasons() -> anyhow::Result<()> {
+        let db = seeded()?;
+        db.add_game(&Game {
+            id: 2024020001,
+            date: NaiveDate::from_ymd_opt(2024, 10, 8).unwrap(),
+            season: 20242025,
+            game_type: GameType::RegularSeason,
+            away_id: 8,
+            home_id: 10,
+            score: (3, 2),
+            resolution: PeriodType::Regulation,
+        })?;
+        let mut carried = State::with_config(
+            &db,
+            StateConfig {
+                ranker: RankerConfig {
+                    season: SeasonTransform::NONE,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        carried.replay(10)?;
+        let gap = db.get_team(8)?.rating.rating - db.get_team(10)?.rating.rating;
+
+        let mut regressed = State::from(&db);
+        regressed.replay(10)?;
+        let regressed_gap = db.get_team(8)?.rating.rating - db.get_team(10)?.rating.rating;
+        assert!(regressed_gap < gap);
+        Ok(())
+    }
+
+    #[test]
+    fn relocations_carry_over_when_the_new_team_starts() -> anyhow::Result<()> {
+        let db = DataBase::new(":memory:")?;
+        let teams = [
+            (10, "Toronto Maple Leafs", "TOR"),
+            (59, "Utah Hockey Club", "UTA"),
+            (68, "Utah Mammoth", "UTA"),
+        ];
+        for (id, name, abbrev) in teams {
+            db.add_team(id, name.to_string(), abbrev.to_string())?;
+            db.add_last10(id)?;
+        }
+        for ((id1, ..), (id2, ..)) in teams.iter().tuple_combinations() {
+            let (team1, team2) = (db.get_team(*id1)?, db.get_team(*id2)?);
+            db.add_h2h(&team1.vs(&team2))?;
+            db.add_h2h(&team2.vs(&team1))?;
+        }
+        let franchise = |id| matches!(id, 59 | 68).then_some(40);
+        for era in known_eras(franchise) {
+            db.add_team_era(&era)?;
+        }
+        let games = [
+            (2024020001, (2024, 10, 8), 59, (4, 1)),
+            (2024020002, (2024, 11, 8), 59, (3, 1)),
+            (2025020001, (2025, 10, 8), 68, (1, 2)),
+        ];
+        for (id, (y, m, d), away_id, score) in games {
+            let date = NaiveDate::from_ymd_opt(y, m, d).unwrap();
+            db.add_game(&Game {
+                id,
+                date,
+                season: season_from_date(date),
+                game_type: GameType::RegularSeason,
+                away_id,
+                home_id: 10,
+                score,
+                resolution: PeriodType::Regulation,
+            })?;
+        }
+        let replay = |carry_over| -> anyhow::Result<_> {
+            let config = StateConfig {
+                models: vec![ModelKind::Ranking],
+                ranker: RankerConfig {
+                    season: SeasonTransform::NONE,
+                    carry_over,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            State::with_config(&db, config).replay(10)?;
+            Ok(db.get_team(68)?.rating)
+        };
+        let fresh = replay(false)?;
+        let carried = replay(true)?;
+        // The Mammoth start from Utah's two wins, not from scratch
+        assert!(carried.rating > fresh.rating);
+        assert!(carried.uncertainty < fresh.uncertainty);
+        Ok(())
+    }
+
+    #[test]
+    fn overtime_losses_are_partial() -> anyhow::Result<()> {
+        let db = seeded()?;
+        db.add_game(&Game {
+            id: 2023020010,
+            date: NaiveDate::from_ymd_opt(2023, 11, 1).unwrap(),
+            season: 20232024,
+            game_type: GameType::RegularSeason,
+            away_id: 8,
+            home_id: 10,
+            score: (3, 4),
+            resolution: PeriodType::Shootout,
+        })?;
+        let mut state = State::with_config(
+            &db,
+            StateConfig {
+                overtime: OvertimePolicy::Draw,
+                ..Default::default()
+            },
+        );
+        state.replay(10)?;
+        let h2h = db.get_h2h(8, 10)?;
+        assert_eq!((h2h.team_wins, h2h.ot_losses, h2h.total_games), (2, 1, 4));
+        assert_eq!(h2h.team_win_freq, 2.5 / 4.);
+        let last10 = db.get_last10(8)?;
+        assert_eq!((last10.wins, last10.loss, last10.otl), (2, 1, 1));
+        assert_eq!((last10.played, last10.games.len()), (4, 4));
+        assert_eq!(db.get_last10(10)?.wins, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn other_systems_rate_on_the_side() -> anyhow::Result<()> {
+        let db = seeded()?;
+        let mut state = State::with_config(
+            &db,
+            StateConfig {
+                ranker: RankerConfig {
+                    system: RatingSystem::Elo,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        state.replay(10)?;
+        assert_eq!(db.get_team(8)?.rating.rating, 25.0);
+        let elo = db.get_rating(8, RatingSystem::Elo)?;
+        assert!(elo.mean() > RatingSystem::Elo.new_rating().mean());
+
+        db.load_memory()?;
+        state.replay(10)?;
+        db.unload_memory()?;
+        assert_eq!(db.get_rating(8, RatingSystem::Elo)?, elo);
+        Ok(())
+    }
+
+    #[test]
+    fn memory_replay_matches_sqlite() -> anyhow::Result<()> {
+        let db = seeded()?;
+        let mut state = State::from(&db);
+        state.replay(1)?;
+        let expected = db.get_team(10)?.rating;
+
+        db.reset_state()?;
+        db.load_memory()?;
+        state.replay(1)?;
+        assert_eq!(db.get_team(10)?.rating, expected);
+        assert!(db.is_processed(2023020001)?);
+        db.unload_memory()?;
+        assert_eq!(db.get_team(10)?.rating, expected);
+        assert_eq!(db.get_h2h(8, 10)?.total_games, 3);
+        assert!(db.is_processed(2023020001)?);
+        Ok(())
+    }
+
+    #[test]
+    fn runs_over_a_memory_store() -> anyhow::Result<()> {
+        let db = seeded()?;
+        State::from(&db).replay(10)?;
+        let store = Arc::new(MemoryStore::new());
+        for game in db.get_games()? {
+            assert!(store.add_game(&game)?);
+        }
+        let mut state = State::from(Arc::clone(&store));
+        let worker = std::thread::spawn(move || -> rusqlite::Result<_> {
+            state.replay(2)?;
+            Ok(state)
+        });
+        let state = worker.join().unwrap()?;
+        assert_eq!(state.get_metrics()[0].total.games, 3);
+        assert_eq!(store.last_processed_season()?, Some(20232024));
+        assert_eq!(
+            store.get_rating(8, RatingSystem::WengLin)?,
+            db.get_rating(8, RatingSystem::WengLin)?
+        );
+        assert_eq!(
+            store.get_h2h(8, 10)?.team_win_freq,
+            db.get_h2h(8, 10)?.team_win_freq
+        );
+        assert_eq!(store.get_strength(10)?, db.get_strength(10)?);
+
+        // Toronto's records follow it into the next season under a new id
+        store.add_succession(10, 59, NaiveDate::from_ymd_opt(2024, 7, 1).unwrap());
+        store.add_game(&Game {
+            id: 2024020001,
+            date: NaiveDate::from_ymd_opt(2024, 10, 8).unwrap(),
+            season: 20242025,
+            game_type: GameType::RegularSeason,
+            away_id: 8,
+            home_id: 59,
+            score: (2, 3),
+            resolution: PeriodType::Regulation,
+        })?;
+        let mut state = State::from(Arc::clone(&store));
+        assert_eq!(state.replay(10)?, 4);
+        assert_eq!(store.get_h2h(8, 59)?.total_games, 4);
+        assert!(store.is_processed(2024020001)?);
+        Ok(())
     }
 }
@@ -1 +1,6 @@
+pub mod home;
+pub mod margin;
 pub mod openskill;
+pub mod overtime;
+pub mod season;
+pub mod system;
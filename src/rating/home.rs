@@ -0,0 +1,45 @@
+use crate::rating::system::{Rating, RatingSystem, SystemConfig};
+
+/// Home teams' share of the results over the NHL's regular seasons, ties
+/// counting half, which the home advantage is set from unless given
+pub const HOME_SCORE: f64 = 0.55;
+
+/// Rating offset that gives the home side of two teams rated `team` an
+/// expected score of `home_score`
+pub fn offset_for(home_score: f64, team: Rating, config: &SystemConfig) -> f64 {
+    let system = team.system();
+    let home_exp = |offset: f64| {
+        let home = team.with_mean(team.mean() + offset);
+        system.expected_score(config, &team, &home).1
+    };
+    let bound = 25. * system.scale();
+    let (mut lo, mut hi) = (-bound, bound);
+    for _ in 0..64 {
+        let mid = (lo + hi) / 2.;
+        if home_exp(mid) < home_score {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    (lo + hi) / 2.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_home_record() {
+        let config = SystemConfig::default();
+        for system in RatingSystem::ALL {
+            let team = system.new_rating();
+            assert!(offset_for(0.5, team, &config).abs() < 1e-6);
+            let offset = offset_for(0.55, team, &config);
+            assert!(offset > 0.);
+            let home = team.with_mean(team.mean() + offset);
+            let (_, exp_home) = system.expected_score(&config, &team, &home);
+            assert!((exp_home - 0.55).abs() < 1e-6);
+        }
+    }
+}
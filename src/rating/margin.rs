@@ -0,0 +1,79 @@
+use clap::ValueEnum;
+
+/// How the goal difference grows the size of a rating update
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MarginCurve {
+    /// `1 + scale * ln(margin)`, each extra goal counts for less
+    Log,
+    /// `1 + scale * (margin - 1)`
+    Linear,
+}
+
+/// Weight applied to the winner's rating step. One goal wins get a weight of
+/// one before damping, so the plain update is the baseline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarginWeight {
+    pub curve: MarginCurve,
+    pub scale: f64,
+    /// Shrinks the weight when the winner was the favourite, otherwise
+    /// blowouts by strong teams keep inflating their rating. Upsets grow it
+    /// by at most `1 + damping / 2`.
+    pub damping: f64,
+}
+
+impl MarginWeight {
+    /// `exp_winner` is the winner's expected score before the game
+    pub fn weight(&self, margin: u32, exp_winner: f64) -> f64 {
+        let margin = margin.max(1) as f64;
+        let weight = match self.curve {
+            MarginCurve::Log => 1. + self.scale * margin.ln(),
+            MarginCurve::Linear => 1. + self.scale * (margin - 1.),
+        };
+        // Stays positive down to an expected score of zero, where it peaks
+        let bound = 1. + self.damping / 2.;
+        weight * bound / (bound + self.damping * (exp_winner - 0.5))
+    }
+}
+
+impl Default for MarginWeight {
+    fn default() -> Self {
+        Self {
+            curve: MarginCurve::Log,
+            scale: 0.5,
+            damping: 2.,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blowouts_weigh_more_unless_expected() {
+        let weight = MarginWeight::default();
+        assert_eq!(weight.weight(1, 0.5), 1.);
+        assert!(weight.weight(6, 0.5) > weight.weight(1, 0.5));
+        assert!(weight.weight(6, 0.8) < weight.weight(6, 0.5));
+        assert!(weight.weight(6, 0.2) > weight.weight(6, 0.5));
+    }
+
+    #[test]
+    fn upsets_stay_bounded() {
+        for damping in [0., 2., 5., 50.] {
+            let weight = MarginWeight {
+                damping,
+                ..Default::default()
+            };
+            let bound = 1. + damping / 2.;
+            let underdog = weight.weight(1, 0.);
+            assert!((underdog - bound).abs() < 1e-12);
+            assert!(weight.weight(1, 0.02) <= underdog);
+            let favourite = weight.weight(1, 1.);
+            assert!(favourite > 0. && favourite <= 1.);
+            assert!(weight.weight(6, 0.) <= bound * weight.weight(6, 0.5));
+        }
+        // A 2% upset by one goal under the default damping
+        assert!(MarginWeight::default().weight(1, 0.02) < 2.);
+    }
+}
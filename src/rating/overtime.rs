@@ -0,0 +1,40 @@
+use skillratings::Outcomes;
+
+/// How games decided in overtime or a shootout are scored
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OvertimePolicy {
+    /// Full wins and losses, whatever the period
+    #[default]
+    Binary,
+    /// Both teams get half a win
+    Draw,
+    /// The winner gets this fraction of a win and the loser the rest, `2/3`
+    /// mirrors the two points to one split of the standings
+    Weighted(f64),
+}
+
+impl OvertimePolicy {
+    /// Score of the team whose result is `outcome`, between zero and one.
+    /// Ties always score one half.
+    pub fn score(&self, outcome: Outcomes, overtime: bool) -> f64 {
+        let full = match outcome {
+            Outcomes::WIN => 1.,
+            Outcomes::LOSS => 0.,
+            Outcomes::DRAW => return 0.5,
+        };
+        if !overtime {
+            return full;
+        }
+        match *self {
+            OvertimePolicy::Binary => full,
+            OvertimePolicy::Draw => 0.5,
+            OvertimePolicy::Weighted(weight) => {
+                if outcome == Outcomes::WIN {
+                    weight
+                } else {
+                    1. - weight
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use crate::rating::system::Rating;
+
+/// What happens to a team's rating between two seasons
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeasonTransform {
+    /// Fraction of the gap to the league mean closed over the summer
+    pub regression: f64,
+    /// Added to the uncertainty, which never grows past a new team's. It is
+    /// in Weng-Lin units and scaled to the other systems.
+    pub inflation: f64,
+}
+
+impl SeasonTransform {
+    /// Carries ratings over untouched
+    pub const NONE: SeasonTransform = SeasonTransform {
+        regression: 0.,
+        inflation: 0.,
+    };
+
+    pub fn apply(&self, rating: Rating, mean: f64) -> Rating {
+        let system = rating.system();
+        let rating = rating.with_mean(rating.mean() + self.regression * (mean - rating.mean()));
+        match (rating.uncertainty(), system.new_rating().uncertainty()) {
+            (Some(uncertainty), Some(max)) => {
+                rating.with_uncertainty((uncertainty + self.inflation * system.scale()).min(max))
+            }
+            _ => rating,
+        }
+    }
+}
+
+impl Default for SeasonTransform {
+    fn default() -> Self {
+        Self {
+            regression: 0.3,
+            inflation: 1.,
+        }
+    }
+}
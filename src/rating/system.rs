@@ -0,0 +1,246 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use skillratings::{
+    Outcomes,
+    elo::{self, EloConfig, EloRating},
+    glicko2::{self, Glicko2Config, Glicko2Rating},
+    sticko::{self, StickoConfig, StickoRating},
+    trueskill::{self, TrueSkillConfig, TrueSkillRating},
+    weng_lin::{self, WengLinConfig, WengLinRating},
+};
+
+use crate::rating::openskill::{RATING_CONFIG, SkillRating};
+
+/// The rating algorithms the ranker can run on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, ValueEnum)]
+pub enum RatingSystem {
+    #[default]
+    WengLin,
+    Elo,
+    Glicko2,
+    TrueSkill,
+    Sticko,
+}
+
+impl RatingSystem {
+    pub const ALL: [RatingSystem; 5] = [
+        RatingSystem::WengLin,
+        RatingSystem::Elo,
+        RatingSystem::Glicko2,
+        RatingSystem::TrueSkill,
+        RatingSystem::Sticko,
+    ];
+
+    /// Name the system's ratings are stored under
+    pub fn name(&self) -> &'static str {
+        match self {
+            RatingSystem::WengLin => "weng_lin",
+            RatingSystem::Elo => "elo",
+            RatingSystem::Glicko2 => "glicko2",
+            RatingSystem::TrueSkill => "trueskill",
+            RatingSystem::Sticko => "sticko",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<RatingSystem> {
+        Self::ALL.into_iter().find(|system| system.name() == name)
+    }
+
+    /// Rating of a team that hasn't played yet
+    pub fn new_rating(&self) -> Rating {
+        match self {
+            RatingSystem::WengLin => Rating::WengLin(WengLinRating::new()),
+            RatingSystem::Elo => Rating::Elo(EloRating::new()),
+            RatingSystem::Glicko2 => Rating::Glicko2(Glicko2Rating::new()),
+            RatingSystem::TrueSkill => Rating::TrueSkill(TrueSkillRating::new()),
+            RatingSystem::Sticko => Rating::Sticko(StickoRating::new()),
+        }
+    }
+
+    /// Size of the system's rating units next to Weng-Lin's, going by the
+    /// uncertainty of a new rating. Offsets configured in Weng-Lin units are
+    /// multiplied by it.
+    pub fn scale(&self) -> f64 {
+        match self {
+            RatingSystem::WengLin | RatingSystem::TrueSkill => 1.,
+            RatingSystem::Elo | RatingSystem::Glicko2 | RatingSystem::Sticko => 350. / (25. / 3.),
+        }
+    }
+
+    /// Expected scores of `one` against `two`
+    ///
+    /// # Panics
+    ///
+    /// When the ratings belong to another system
+    pub fn expected_score(&self, config: &SystemConfig, one: &Rating, two: &Rating) -> (f64, f64) {
+        match (one, two) {
+            (Rating::WengLin(one), Rating::WengLin(two)) => {
+                weng_lin::expected_score(one, two, &config.weng_lin)
+            }
+            (Rating::Elo(one), Rating::Elo(two)) => elo::expected_score(one, two),
+            (Rating::Glicko2(one), Rating::Glicko2(two)) => glicko2::expected_score(one, two),
+            (Rating::TrueSkill(one), Rating::TrueSkill(two)) => {
+                trueskill::expected_score(one, two, &config.trueskill)
+            }
+            (Rating::Sticko(one), Rating::Sticko(two)) => {
+                sticko::expected_score(one, two, &config.sticko)
+            }
+            _ => panic!("{} can't compare {one:?} and {two:?}", self.name()),
+        }
+    }
+
+    /// New ratings of `one` and `two` after a game with `outcome` for `one`
+    ///
+    /// # Panics
+    ///
+    /// When the ratings belong to another system
+    pub fn rate(
+        &self,
+        config: &SystemConfig,
+        one: &Rating,
+        two: &Rating,
+        outcome: &Outcomes,
+    ) -> (Rating, Rating) {
+        match (one, two) {
+            (Rating::WengLin(one), Rating::WengLin(two)) => {
+                let (one, two) = weng_lin::weng_lin(one, two, outcome, &config.weng_lin);
+                (Rating::WengLin(one), Rating::WengLin(two))
+            }
+            (Rating::Elo(one), Rating::Elo(two)) => {
+                let (one, two) = elo::elo(one, two, outcome, &config.elo);
+                (Rating::Elo(one), Rating::Elo(two))
+            }
+            (Rating::Glicko2(one), Rating::Glicko2(two)) => {
+                let (one, two) = glicko2::glicko2(one, two, outcome, &config.glicko2);
+                (Rating::Glicko2(one), Rating::Glicko2(two))
+            }
+            (Rating::TrueSkill(one), Rating::TrueSkill(two)) => {
+                let (one, two) = trueskill::trueskill(one, two, outcome, &config.trueskill);
+                (Rating::TrueSkill(one), Rating::TrueSkill(two))
+            }
+            (Rating::Sticko(one), Rating::Sticko(two)) => {
+                let (one, two) = sticko::sticko(one, two, outcome, &config.sticko);
+                (Rating::Sticko(one), Rating::Sticko(two))
+            }
+            _ => panic!("{} can't rate {one:?} and {two:?}", self.name()),
+        }
+    }
+}
+
+/// Tuning of every system, only the chosen system's is used
+#[derive(Debug, Clone, Copy)]
+pub struct SystemConfig {
+    pub weng_lin: WengLinConfig,
+    pub elo: EloConfig,
+    pub glicko2: Glicko2Config,
+    pub trueskill: TrueSkillConfig,
+    pub sticko: StickoConfig,
+}
+
+impl Default for SystemConfig {
+    fn default() -> Self {
+        Self {
+            weng_lin: RATING_CONFIG,
+            elo: EloConfig::new(),
+            glicko2: Glicko2Config::new(),
+            trueskill: TrueSkillConfig::new(),
+            sticko: StickoConfig::new(),
+        }
+    }
+}
+
+/// A team's rating in any of the [`RatingSystem`]s
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Rating {
+    WengLin(WengLinRating),
+    Elo(EloRating),
+    Glicko2(Glicko2Rating),
+    TrueSkill(TrueSkillRating),
+    Sticko(StickoRating),
+}
+
+impl Rating {
+    pub fn system(&self) -> RatingSystem {
+        match self {
+            Rating::WengLin(_) => RatingSystem::WengLin,
+            Rating::Elo(_) => RatingSystem::Elo,
+            Rating::Glicko2(_) => RatingSystem::Glicko2,
+            Rating::TrueSkill(_) => RatingSystem::TrueSkill,
+            Rating::Sticko(_) => RatingSystem::Sticko,
+        }
+    }
+
+    pub fn mean(&self) -> f64 {
+        match self {
+            Rating::WengLin(rating) => rating.rating,
+            Rating::Elo(rating) => rating.rating,
+            Rating::Glicko2(rating) => rating.rating,
+            Rating::TrueSkill(rating) => rating.rating,
+            Rating::Sticko(rating) => rating.rating,
+        }
+    }
+
+    pub fn with_mean(mut self, mean: f64) -> Self {
+        match &mut self {
+            Rating::WengLin(rating) => rating.rating = mean,
+            Rating::Elo(rating) => rating.rating = mean,
+            Rating::Glicko2(rating) => rating.rating = mean,
+            Rating::TrueSkill(rating) => rating.rating = mean,
+            Rating::Sticko(rating) => rating.rating = mean,
+        }
+        self
+    }
+
+    /// The uncertainty or deviation, `None` for Elo
+    pub fn uncertainty(&self) -> Option<f64> {
+        match self {
+            Rating::WengLin(rating) => Some(rating.uncertainty),
+            Rating::Elo(_) => None,
+            Rating::Glicko2(rating) => Some(rating.deviation),
+            Rating::TrueSkill(rating) => Some(rating.uncertainty),
+            Rating::Sticko(rating) => Some(rating.deviation),
+        }
+    }
+
+    /// Sets the uncertainty, does nothing for Elo
+    pub fn with_uncertainty(mut self, uncertainty: f64) -> Self {
+        match &mut self {
+            Rating::WengLin(rating) => rating.uncertainty = uncertainty,
+            Rating::Elo(_) => {}
+            Rating::Glicko2(rating) => rating.deviation = uncertainty,
+            Rating::TrueSkill(rating) => rating.uncertainty = uncertainty,
+            Rating::Sticko(rating) => rating.deviation = uncertainty,
+        }
+        self
+    }
+
+    /// Number shown in the rankings, the MMR for Weng-Lin
+    pub fn points(&self) -> i32 {
+        match self {
+            Rating::WengLin(rating) => rating.mmr(),
+            rating => rating.mean().round() as i32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_system_rates_and_round_trips() -> anyhow::Result<()> {
+        let config = SystemConfig::default();
+        for system in RatingSystem::ALL {
+            assert_eq!(RatingSystem::from_name(system.name()), Some(system));
+            let rating = system.new_rating();
+            assert_eq!(rating.system(), system);
+            let (winner, loser) = system.rate(&config, &rating, &rating, &Outcomes::WIN);
+            assert!(winner.mean() > loser.mean());
+            let (exp_winner, _) = system.expected_score(&config, &winner, &loser);
+            assert!(exp_winner > 0.5);
+            let json = serde_json::to_string(&winner)?;
+            assert_eq!(serde_json::from_str::<Rating>(&json)?, winner);
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,8 @@
-pub mod ids;
-
+use chrono::{Datelike, NaiveDate};
 use skillratings::Outcomes;
 
+use crate::data::models::games::Game;
+
 pub const EPSILON: f64 = 1e-5;
 
 pub fn outcome_from_prob(exp_away: f64, exp_home: f64) -> Outcomes {
@@ -14,6 +15,27 @@ pub fn outcome_from_prob(exp_away: f64, exp_home: f64) -> Outcomes {
     }
 }
 
+/// Season id a game belongs to, worked out from its date when the game
+/// doesn't carry one
+pub fn season_of(game: &Game) -> u32 {
+    if game.season != 0 {
+        game.season
+    } else {
+        season_from_date(game.date)
+    }
+}
+
+/// Seasons start in the fall, so anything before September belongs to the
+/// season that started the previous year
+pub fn season_from_date(date: NaiveDate) -> u32 {
+    let start = if date.month() >= 9 {
+        date.year()
+    } else {
+        date.year() - 1
+    } as u32;
+    start * 10_000 + start + 1
+}
+
 pub fn in_season(_year: i32, month: u32, _day: u32) -> bool {
     month >= 10 || 4 >= month
 }
@@ -1,12 +0,0 @@
-// fn id_from_abbrev(abbrev: &str) -> Option<u64> {
-//     match abbrev {
-//         "MTL" => Some(8),
-//         "BUF" => Some(7),
-//         "NYI" => Some(2),
-//         "SJS" => Some(28),
-//         "CBJ" => Some(42),
-//         "MIN" => Some()
-//         "UTA" => Some(59),
-//         _ => None
-//     }
-// }